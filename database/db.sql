@@ -10,10 +10,15 @@ INSERT INTO account_families (name) VALUES
     ('Income'),
     ('Expense');
 
+-- Accounts form a tree, children share the family of their parent, see check_account_tree().
+-- Names are unique among siblings so that the path of an account, its name and the
+-- names of its ancestors joined by ':' (e.g. Expense:Housing:Rent), identifies it.
 CREATE TABLE IF NOT EXISTS accounts (
     id SERIAL PRIMARY KEY,
-    name VARCHAR(256),
-    family INTEGER NOT NULL REFERENCES account_families(id) ON DELETE RESTRICT
+    name VARCHAR(256) NOT NULL CHECK (name <> '' AND position(':' IN name) = 0),
+    family INTEGER NOT NULL REFERENCES account_families(id) ON DELETE RESTRICT,
+    parent INTEGER REFERENCES accounts(id) ON DELETE RESTRICT,
+    CONSTRAINT accounts_parent_name_key UNIQUE NULLS NOT DISTINCT (parent, name)
 );
 
 CREATE TABLE IF NOT EXISTS entries 
@@ -21,9 +26,17 @@ CREATE TABLE IF NOT EXISTS entries
     id SERIAL PRIMARY KEY,
     description VARCHAR(1024) UNIQUE NOT NULL,
     event_date TIMESTAMPTZ NOT NULL,
-    amount NUMERIC(20, 2) NOT NULL CHECK (amount > 0.0),
-    credit INTEGER NOT NULL REFERENCES accounts(id) ON DELETE RESTRICT,
-    debit INTEGER NOT NULL REFERENCES accounts(id) ON DELETE RESTRICT
+    reverses INTEGER UNIQUE REFERENCES entries(id) ON DELETE RESTRICT -- Counter-entry of a corrected entry
+);
+
+-- Legs of an entry: positive amounts are credited to the account, negative ones debited.
+-- The amounts of an entry sum to zero, see check_balanced_entry()
+CREATE TABLE IF NOT EXISTS postings
+(
+    id SERIAL PRIMARY KEY,
+    entry INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+    account INTEGER NOT NULL REFERENCES accounts(id) ON DELETE RESTRICT,
+    amount NUMERIC(20, 2) NOT NULL CHECK (amount <> 0.0)
 );
 
 -- Side notes:
@@ -38,12 +51,44 @@ CREATE TABLE IF NOT EXISTS recurrences
     start_date DATE,
     end_date DATE, -- Inclusive
     amount NUMERIC(20, 2) NOT NULL CHECK (amount > 0.0),
-    frequence FREQUENCE
+    frequence FREQUENCE NOT NULL
+);
+
+-- Amount planned for an account and its sub-accounts every month or year, dropped with the account
+CREATE TABLE IF NOT EXISTS budgets
+(
+    id SERIAL PRIMARY KEY,
+    account INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    amount NUMERIC(20, 2) NOT NULL CHECK (amount > 0.0),
+    frequence FREQUENCE NOT NULL CHECK (frequence IN ('monthly', 'yearly')),
+    CONSTRAINT budgets_account_frequence_key UNIQUE (account, frequence)
 );
 
+-- Money earmarked for Expense accounts used as envelopes, NULL standing for the to be budgeted
+-- pool. Nothing is posted: the money stays on the Asset accounts until entries spend it.
+CREATE TABLE IF NOT EXISTS envelope_transfers
+(
+    id SERIAL PRIMARY KEY,
+    month DATE NOT NULL CHECK (extract(day FROM month) = 1),
+    source INTEGER REFERENCES accounts(id) ON DELETE CASCADE,
+    target INTEGER REFERENCES accounts(id) ON DELETE CASCADE,
+    amount NUMERIC(20, 2) NOT NULL CHECK (amount > 0.0),
+    CHECK (source IS DISTINCT FROM target AND (source IS NOT NULL OR target IS NOT NULL))
+);
+
+-- Entries posted from a recurrence, at most one per occurrence day
+ALTER TABLE entries
+    ADD COLUMN recurrence INTEGER REFERENCES recurrences(id) ON DELETE SET NULL,
+    ADD COLUMN occurrence DATE,
+    ADD CONSTRAINT entries_recurrence_occurrence_key UNIQUE (recurrence, occurrence);
+
 CREATE INDEX ON accounts(family);
-CREATE INDEX ON entries(credit);
-CREATE INDEX ON entries(debit);
+CREATE INDEX ON accounts(parent);
+CREATE INDEX ON entries(event_date);
+CREATE INDEX ON envelope_transfers(source);
+CREATE INDEX ON envelope_transfers(target);
+CREATE INDEX ON postings(entry);
+CREATE INDEX ON postings(account);
 CREATE INDEX ON recurrences(credit);
 CREATE INDEX ON recurrences(debit);
 
@@ -70,16 +115,153 @@ CREATE VIEW account_ledgers(
     amount
 ) AS
     SELECT
-        entries.credit,
+        postings.account,
+        postings.entry,
+        postings.amount
+    FROM postings;
+
+-- Entries made of exactly one credited and one debited account
+CREATE VIEW pair_entries(
+    id,
+    description,
+    event_date,
+    amount,
+    credit,
+    debit,
+    reverses
+) AS
+    SELECT
         entries.id,
-        entries.amount
+        entries.description,
+        entries.event_date,
+        credited.amount,
+        credited.account,
+        debited.account,
+        entries.reverses
     FROM entries
-    UNION ALL
+        JOIN postings credited ON credited.entry = entries.id AND credited.amount > 0.0
+        JOIN postings debited ON debited.entry = entries.id AND debited.amount < 0.0
+    WHERE (SELECT count(*) FROM postings WHERE postings.entry = entries.id) = 2;
+
+-- Every entry, split ones included, once per pair of a credited and a debited posting.
+-- Columns match pair_entries so entry searches apply, the amount is the total credited by the entry.
+CREATE VIEW transaction_legs(
+    id,
+    description,
+    event_date,
+    amount,
+    credit,
+    debit,
+    reverses
+) AS
     SELECT
-        entries.debit,
         entries.id,
-        (0.0 - entries.amount)
-    FROM entries;
+        entries.description,
+        entries.event_date,
+        (SELECT sum(postings.amount) FROM postings WHERE postings.entry = entries.id AND postings.amount > 0.0),
+        credited.account,
+        debited.account,
+        entries.reverses
+    FROM entries
+        JOIN postings credited ON credited.entry = entries.id AND credited.amount > 0.0
+        JOIN postings debited ON debited.entry = entries.id AND debited.amount < 0.0;
+
+-- Checked at commit, once every posting of the entry is written
+CREATE FUNCTION check_balanced_entry() RETURNS TRIGGER AS $$
+DECLARE
+    entry_id INTEGER;
+    postings_count INTEGER;
+    total NUMERIC;
+BEGIN
+    IF TG_TABLE_NAME = 'entries' THEN
+        entry_id := NEW.id;
+    ELSIF TG_OP = 'DELETE' THEN
+        entry_id := OLD.entry;
+    ELSE
+        entry_id := NEW.entry;
+    END IF;
+
+    SELECT count(*), COALESCE(sum(postings.amount), 0.0)
+    INTO postings_count, total
+    FROM postings
+    WHERE postings.entry = entry_id;
+
+    -- Deleting the whole entry removes all of its postings
+    IF postings_count = 0 AND NOT EXISTS (SELECT 1 FROM entries WHERE entries.id = entry_id) THEN
+        RETURN NULL;
+    END IF;
+
+    IF postings_count < 2 THEN
+        RAISE EXCEPTION 'Entry % needs at least two postings, found %', entry_id, postings_count
+            USING ERRCODE = 'check_violation';
+    END IF;
+    IF total <> 0.0 THEN
+        RAISE EXCEPTION 'Postings of entry % sum to %, not zero', entry_id, total
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER trigger_check_balanced_entry
+AFTER INSERT OR UPDATE OR DELETE
+ON postings
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW
+    EXECUTE PROCEDURE check_balanced_entry();
+
+-- Entries without any posting
+CREATE CONSTRAINT TRIGGER trigger_check_posted_entry
+AFTER INSERT
+ON entries
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW
+    EXECUTE PROCEDURE check_balanced_entry();
+
+-- A child shares the family of its parent, and is never one of its own ancestors
+CREATE FUNCTION check_account_tree() RETURNS TRIGGER AS $$
+DECLARE
+    parent_family INTEGER;
+BEGIN
+    IF NEW.parent IS NOT NULL THEN
+        SELECT family INTO parent_family FROM accounts WHERE id = NEW.parent;
+        IF NOT FOUND THEN
+            RAISE EXCEPTION 'Parent account % does not exist', NEW.parent
+                USING ERRCODE = 'check_violation';
+        END IF;
+        IF parent_family <> NEW.family THEN
+            RAISE EXCEPTION 'Account % must share the family of its parent %', NEW.name, NEW.parent
+                USING ERRCODE = 'check_violation';
+        END IF;
+
+        IF TG_OP = 'UPDATE' AND EXISTS (
+            WITH RECURSIVE ancestors(id, parent) AS (
+                SELECT id, parent FROM accounts WHERE id = NEW.parent
+                UNION
+                SELECT accounts.id, accounts.parent
+                FROM accounts JOIN ancestors ON accounts.id = ancestors.parent
+            )
+            SELECT 1 FROM ancestors WHERE id = NEW.id
+        ) THEN
+            RAISE EXCEPTION 'Account % cannot be moved under its own sub-account %', NEW.name, NEW.parent
+                USING ERRCODE = 'check_violation';
+        END IF;
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND NEW.family <> OLD.family
+        AND EXISTS (SELECT 1 FROM accounts WHERE parent = NEW.id) THEN
+        RAISE EXCEPTION 'Account % has sub-accounts, its family cannot change', NEW.name
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_check_account_tree
+BEFORE INSERT OR UPDATE OF family, parent
+ON accounts
+FOR EACH ROW
+    EXECUTE PROCEDURE check_account_tree();
 
 CREATE MATERIALIZED VIEW account_balances(
     -- Materialized so financial reports run fast
@@ -104,11 +286,11 @@ BEGIN
 END
 $$ LANGUAGE plpgsql;
 
-CREATE TRIGGER trigger_fix_balance_entries
+CREATE TRIGGER trigger_fix_balance_postings
 AFTER INSERT
-OR UPDATE OF amount, credit, debit
+OR UPDATE OF amount, account
 OR DELETE OR TRUNCATE
-ON entries
+ON postings
 FOR EACH STATEMENT
     EXECUTE PROCEDURE update_balances();
 
@@ -119,3 +301,97 @@ OR DELETE OR TRUNCATE
 ON accounts
 FOR EACH STATEMENT
     EXECUTE PROCEDURE update_balances();
+
+-- Realtime notifications, one JSON payload per changed row
+CREATE FUNCTION notify_accounts() RETURNS TRIGGER AS $$
+DECLARE
+    account RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        account := OLD;
+    ELSE
+        account := NEW;
+    END IF;
+
+    PERFORM pg_notify('accounts', json_build_object(
+        'operation', TG_OP,
+        'id', account.id,
+        'name', account.name,
+        'family', account.family,
+        'parent', account.parent
+    )::text);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_notify_accounts
+AFTER INSERT OR UPDATE OR DELETE
+ON accounts
+FOR EACH ROW
+    EXECUTE PROCEDURE notify_accounts();
+
+-- Postings are written after their entry, listeners read them once notified.
+-- Updates and deletions run before the postings change, so the accounts they
+-- move away from are still known.
+CREATE FUNCTION notify_entries() RETURNS TRIGGER AS $$
+DECLARE
+    entry RECORD;
+    accounts JSON;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        entry := OLD;
+    ELSE
+        entry := NEW;
+    END IF;
+
+    IF TG_OP <> 'INSERT' THEN
+        SELECT json_agg(DISTINCT postings.account)
+        INTO accounts
+        FROM postings
+        WHERE postings.entry = entry.id;
+    END IF;
+
+    PERFORM pg_notify('entries', json_build_object(
+        'operation', TG_OP,
+        'id', entry.id,
+        'accounts', COALESCE(accounts, '[]'::json)
+    )::text);
+    RETURN entry;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_notify_entries
+AFTER INSERT
+ON entries
+FOR EACH ROW
+    EXECUTE PROCEDURE notify_entries();
+
+CREATE TRIGGER trigger_notify_entries_changes
+BEFORE UPDATE OR DELETE
+ON entries
+FOR EACH ROW
+    EXECUTE PROCEDURE notify_entries();
+
+CREATE FUNCTION notify_recurrences() RETURNS TRIGGER AS $$
+DECLARE
+    recurrence RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        recurrence := OLD;
+    ELSE
+        recurrence := NEW;
+    END IF;
+
+    PERFORM pg_notify('recurrences', json_build_object(
+        'operation', TG_OP,
+        'id', recurrence.id
+    )::text);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_notify_recurrences
+AFTER INSERT OR UPDATE OR DELETE
+ON recurrences
+FOR EACH ROW
+    EXECUTE PROCEDURE notify_recurrences();
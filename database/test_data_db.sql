@@ -40,10 +40,11 @@ BEGIN; -- Transaction
     FROM account_families af
     WHERE af.name = 'Expense';
     
-    INSERT INTO accounts (name, family) 
-    SELECT 'Internet', id 
-    FROM account_families af
-    WHERE af.name = 'Services';
+    -- Sub-account, its path is Services:Internet
+    INSERT INTO accounts (name, family, parent) 
+    SELECT 'Internet', family, id 
+    FROM accounts
+    WHERE name = 'Services';
     
     INSERT INTO accounts (name, family) 
     SELECT 'Tax', id 
@@ -56,52 +57,54 @@ BEGIN; -- Transaction
     WHERE af.name = 'Income';
 END; -- Transaction
 
-BEGIN;
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Salary for December', '2024-11-28 11:30:30', 10000, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Salary'
-    AND a2.name = 'Bank';
-
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Insurance stuffs', '2024-12-01 10:00:00', 200, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Insurance';
-        
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Electrical bill', '2024-11-30 10:00:00', 79.0, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Electricity';
-
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Fitness park', '2024-11-30 10:00:00', 29.99, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Fitness park';
+-- Entry of a single amount moved from the debit to the credit account,
+-- skipped when one of the accounts does not exist
+CREATE FUNCTION pg_temp.insert_pair_entry(
+    entry_description TEXT,
+    entry_date TIMESTAMPTZ,
+    entry_amount NUMERIC,
+    credit_name TEXT,
+    debit_name TEXT
+) RETURNS VOID AS $$
+DECLARE
+    credit_id INTEGER;
+    debit_id INTEGER;
+    entry_id INTEGER;
+BEGIN
+    SELECT id INTO credit_id FROM accounts WHERE name = credit_name;
+    SELECT id INTO debit_id FROM accounts WHERE name = debit_name;
+    IF credit_id IS NULL OR debit_id IS NULL THEN
+        RETURN;
+    END IF;
 
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Renting for December', '2024-11-29 15:00:00', 1000.0, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Rent';
+    INSERT INTO entries (description, event_date)
+    VALUES (entry_description, entry_date)
+    RETURNING id INTO entry_id;
 
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Youtube music', '2024-11-29 15:00:00', 15.0, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Services';
+    INSERT INTO postings (entry, account, amount)
+    VALUES (entry_id, credit_id, entry_amount), (entry_id, debit_id, 0.0 - entry_amount);
+END
+$$ LANGUAGE plpgsql;
 
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Internet bill for december', '2024-12-02 15:00:00', 30.0, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Internet';
+BEGIN;
+    SELECT pg_temp.insert_pair_entry('Salary for December', '2024-11-28 11:30:30', 10000, 'Bank', 'Salary');
+    SELECT pg_temp.insert_pair_entry('Insurance stuffs', '2024-12-01 10:00:00', 200, 'Insurance', 'Bank');
+    SELECT pg_temp.insert_pair_entry('Electrical bill', '2024-11-30 10:00:00', 79.0, 'Electricity', 'Bank');
+    SELECT pg_temp.insert_pair_entry('Fitness park', '2024-11-30 10:00:00', 29.99, 'Fitness park', 'Bank');
+    SELECT pg_temp.insert_pair_entry('Renting for December', '2024-11-29 15:00:00', 1000.0, 'Rent', 'Bank');
+    SELECT pg_temp.insert_pair_entry('Youtube music', '2024-11-29 15:00:00', 15.0, 'Services', 'Bank');
+    SELECT pg_temp.insert_pair_entry('Internet bill for december', '2024-12-02 15:00:00', 30.0, 'Internet', 'Bank');
+    SELECT pg_temp.insert_pair_entry('Prelevement a la source', '2024-12-02 15:00:00', 3000.0, 'Tax', 'Bank');
 
-    INSERT INTO entries (description, event_date, amount, credit, debit)
-    SELECT 'Prelevement a la source', '2024-12-02 15:00:00', 3000.0, a2.id, a1.id
-    FROM accounts a1, accounts a2
-    WHERE a1.name = 'Bank'
-    AND a2.name = 'Tax';
+    -- Split entry: one bank payment shared by two expenses
+    WITH entry AS (
+        INSERT INTO entries (description, event_date)
+        VALUES ('Utilities for December', '2024-12-03 09:00:00')
+        RETURNING id
+    )
+    INSERT INTO postings (entry, account, amount)
+    SELECT entry.id, accounts.id, legs.amount
+    FROM entry, accounts
+        JOIN (VALUES ('Bank', -65.0), ('Electricity', 45.0), ('Services', 20.0)) AS legs(name, amount)
+        ON legs.name = accounts.name;
 END;
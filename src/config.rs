@@ -2,9 +2,13 @@ use std::error;
 
 use serde::Deserialize;
 
+use crate::repository::validation::ValidationRules;
+
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub database: Database,
+    #[serde(default)]
+    pub validation: ValidationRules,
 }
 
 #[derive(Deserialize, Debug)]
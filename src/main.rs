@@ -1,6 +1,6 @@
 use rocket::{Config, Rocket};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{Mutex, broadcast};
 use tokio_postgres::NoTls;
 use tracing::{Level, span};
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -10,7 +10,7 @@ use tracing_subscriber::{EnvFilter, Layer};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::repository::RepositoryRealtimeUpdater;
+use crate::repository::{RecurrenceScheduler, RepositoryRealtimeUpdater};
 
 #[macro_use]
 extern crate rocket;
@@ -23,9 +23,24 @@ mod utils;
 
 use crate::routes::ApiDoc;
 use crate::routes::{
-    create_account, create_entry, get_account, get_entries_from_date_to_date, get_entry,
+    create_account, create_budget, create_entry, create_envelope_transfer, create_recurrence,
+    create_transaction, default_catcher, delete_account, delete_budget, delete_entry,
+    delete_envelope_transfer, delete_recurrence, delete_transaction, get_account,
+    get_account_balance, get_account_ledger, get_account_tree, get_accounts, get_balance_sheet,
+    get_balances, get_budget, get_budget_status, get_budgets, get_cash_flow,
+    get_entries_from_date_to_date, get_entry, get_envelope_transfer, get_envelope_transfers,
+    get_envelopes, get_events, get_income_statement, get_net_worth, get_recurrence, get_recurrences,
+    get_transaction, get_transactions, get_trial_balance, patch_account, reverse_entry,
+    sweep_envelopes, update_account, update_budget, update_entry, update_recurrence,
+    update_transaction,
 };
 
+/// How often due recurrences are posted as entries.
+const RECURRENCE_PERIOD: Duration = Duration::from_secs(3600);
+
+/// Live events kept for `/events` subscribers lagging behind.
+const EVENTS_CAPACITY: usize = 256;
+
 #[launch]
 async fn rocket() -> Rocket<rocket::Build> {
 
@@ -48,10 +63,11 @@ async fn rocket() -> Rocket<rocket::Build> {
     let _guard = span.enter();
 
     let config_file = "config.toml".to_string();
-    let database_config = read_config(config_file)
-        .await
-        .expect("Failed to read configuration");
+    let config = config::load_config(config_file).expect("Failed to read configuration");
+    let validation_rules = config.validation;
+    let database_config = pool_config(config.database);
     tracing::event!(parent: &span, Level::INFO, "Configuration file loaded: {:?}", database_config);
+    tracing::event!(parent: &span, Level::INFO, "Validation rules: {:?}", validation_rules);
 
     let pool = database_config
         .create_pool(Some(deadpool_postgres::Runtime::Tokio1), NoTls)
@@ -60,17 +76,29 @@ async fn rocket() -> Rocket<rocket::Build> {
     tracing::event!(parent: &span, Level::INFO, "Database pool is initialized");
 
     // Repository
-    let repository = Arc::new(Mutex::new(repository::Repository::new(db_pool).await));
+    let repository = Arc::new(Mutex::new(
+        repository::Repository::new(db_pool, validation_rules).await,
+    ));
     tracing::event!(parent: &span, Level::INFO, "Repository initialized");
 
     // Notifications from Postgres
+    let (events, _) = broadcast::channel::<model::event::LedgerEvent>(EVENTS_CAPACITY);
     let realtime_update_repo = Arc::clone(&repository);
+    let realtime_events = events.clone();
     let pg_config = database_config.get_pg_config().unwrap();
     tokio::spawn(async move {
-        let realtime_updater = RepositoryRealtimeUpdater::new(realtime_update_repo.clone());
+        let realtime_updater =
+            RepositoryRealtimeUpdater::new(realtime_update_repo.clone(), realtime_events);
         realtime_updater.listen(pg_config, NoTls).await;
     });
 
+    // Recurring entries
+    let scheduler_repo = Arc::clone(&repository);
+    tokio::spawn(async move {
+        let scheduler = RecurrenceScheduler::new(scheduler_repo);
+        scheduler.run(RECURRENCE_PERIOD).await;
+    });
+
     drop(_guard);
 
     let config = rocket::Config::figment().join((Config::CLI_COLORS, "false"));
@@ -79,37 +107,74 @@ async fn rocket() -> Rocket<rocket::Build> {
             "/",
             routes![
                 get_account,
+                get_accounts,
+                get_account_tree,
                 create_account,
+                update_account,
+                patch_account,
+                delete_account,
+                get_account_balance,
+                get_balances,
+                get_balance_sheet,
+                get_income_statement,
+                get_trial_balance,
+                get_cash_flow,
+                get_net_worth,
+                get_account_ledger,
                 get_entry,
                 create_entry,
-                get_entries_from_date_to_date
+                update_entry,
+                delete_entry,
+                reverse_entry,
+                get_entries_from_date_to_date,
+                get_transaction,
+                get_transactions,
+                create_transaction,
+                update_transaction,
+                delete_transaction,
+                get_recurrence,
+                get_recurrences,
+                create_recurrence,
+                update_recurrence,
+                delete_recurrence,
+                get_budget,
+                get_budgets,
+                create_budget,
+                update_budget,
+                delete_budget,
+                get_budget_status,
+                get_envelopes,
+                sweep_envelopes,
+                get_envelope_transfer,
+                get_envelope_transfers,
+                create_envelope_transfer,
+                delete_envelope_transfer,
+                get_events
             ],
         )
+        .register("/", catchers![default_catcher])
         .mount(
             "/",
             SwaggerUi::new("/swagger-ui/<_..>").url("/api-docs/openapi.json", ApiDoc::openapi()),
         )
         .manage(repository)
+        .manage(events)
 }
 
-async fn read_config(
-    config_file: String,
-) -> Result<deadpool_postgres::Config, Box<dyn std::error::Error>> {
-    let config = config::load_config(config_file)?;
-
+fn pool_config(database: config::Database) -> deadpool_postgres::Config {
     let mut deadpool_config = deadpool_postgres::Config::new();
-    deadpool_config.host = Some(config.database.url);
-    deadpool_config.port = config.database.port.map(|p| {
+    deadpool_config.host = Some(database.url);
+    deadpool_config.port = database.port.map(|p| {
         p.parse()
             .expect("Failed to parse port from config.toml. Ensure it's a valid u16.")
     });
 
-    deadpool_config.user = Some(config.database.user);
-    deadpool_config.password = Some(config.database.password);
-    deadpool_config.dbname = Some(config.database.name);
+    deadpool_config.user = Some(database.user);
+    deadpool_config.password = Some(database.password);
+    deadpool_config.dbname = Some(database.name);
     deadpool_config.manager = Some(deadpool_postgres::ManagerConfig {
         recycling_method: deadpool_postgres::RecyclingMethod::Fast,
     });
 
-    Ok(deadpool_config)
+    deadpool_config
 }
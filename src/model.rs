@@ -1,3 +1,13 @@
 pub mod account;
+pub mod balance;
+pub mod budget;
 pub mod entry;
+pub mod envelope;
+pub mod event;
+pub mod ledger;
+pub mod money;
+pub mod problem;
+pub mod recurrence;
+pub mod report;
+pub mod transaction;
 mod test;
\ No newline at end of file
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum AccountFamily {
     Asset,
     Liability,
@@ -10,17 +10,76 @@ pub enum AccountFamily {
     Expense,
 }
 
+impl AccountFamily {
+    /// Every family, in chart of accounts order.
+    pub const ALL: [AccountFamily; 5] = [
+        AccountFamily::Asset,
+        AccountFamily::Liability,
+        AccountFamily::Equity,
+        AccountFamily::Revenue,
+        AccountFamily::Expense,
+    ];
+}
+
+impl std::str::FromStr for AccountFamily {
+    type Err = String;
+
+    /// Parses a family name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AccountFamily::ALL
+            .into_iter()
+            .find(|family| format!("{:?}", family).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown account family '{}'", s))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Account {
+    /// Set by the server, ignored on input.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+
+    /// Name under the parent account, cannot contain `:`.
     pub name: String,
     pub family: AccountFamily,
+
+    /// Account this one is a sub-account of, in the same family. None for top-level accounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i32>,
+
+    /// Names from the top-level account down to this one, e.g. `Expense:Housing:Rent`.
+    /// Set by the server, entries, transactions and recurrences refer to accounts by path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl Account {
+    /// Separates the names of a path.
+    pub const PATH_SEPARATOR: &str = ":";
+
+    /// Path the account is referred to by in an input: its path when given, its name otherwise.
+    pub fn reference(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Partial update of an account, missing fields are left untouched.
+/// A sub-account is moved back to the top level with a full update.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AccountPatch {
+    pub name: Option<String>,
+    pub family: Option<AccountFamily>,
+    pub parent_id: Option<i32>,
 }
 
 impl Clone for Account {
     fn clone(&self) -> Self {
         Account {
+            id: self.id,
             name: self.name.clone(),
             family: self.family.clone(),
+            parent_id: self.parent_id,
+            path: self.path.clone(),
         }
     }
 }
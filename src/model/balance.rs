@@ -0,0 +1,136 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::{Account, AccountFamily};
+use crate::model::money::Money;
+
+/// Signed balance of an account: money received (credit) minus money given (debit).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AccountBalance {
+    pub account: Account,
+    pub balance: Money,
+    /// Balance of the account and of all its sub-accounts.
+    pub subtotal: Money,
+}
+
+impl AccountBalance {
+    /// Adds the balance of every account to the subtotals of all its ancestors.
+    /// Ancestors missing from the balances are skipped.
+    pub fn roll_up(balances: &mut [AccountBalance]) {
+        let parents: HashMap<i32, Option<i32>> = balances
+            .iter()
+            .filter_map(|balance| Some((balance.account.id?, balance.account.parent_id)))
+            .collect();
+
+        let mut subtotals: HashMap<i32, Money> = HashMap::new();
+        for balance in balances.iter() {
+            let mut ancestor = balance.account.parent_id;
+            // Bounded walk, a broken tree cannot loop forever
+            for _ in 0..parents.len() {
+                let Some(id) = ancestor else {
+                    break;
+                };
+                *subtotals.entry(id).or_insert(Money::ZERO) += balance.balance;
+                ancestor = parents.get(&id).copied().flatten();
+            }
+        }
+
+        for balance in balances.iter_mut() {
+            let children_total = balance
+                .account
+                .id
+                .and_then(|id| subtotals.get(&id).copied())
+                .unwrap_or(Money::ZERO);
+            balance.subtotal = balance.balance + children_total;
+        }
+    }
+}
+
+/// Account with its own balance and its sub-accounts, rolled up in the subtotal.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AccountTree {
+    pub account: Account,
+    pub balance: Money,
+    pub subtotal: Money,
+    pub children: Vec<AccountTree>,
+}
+
+impl AccountTree {
+    /// Nests the balances under their parent accounts, keeping their order, and rolls
+    /// up the subtotals. Accounts whose parent is missing are returned at the top level.
+    pub fn build(mut balances: Vec<AccountBalance>) -> Vec<AccountTree> {
+        AccountBalance::roll_up(&mut balances);
+
+        let ids: HashSet<i32> = balances
+            .iter()
+            .filter_map(|balance| balance.account.id)
+            .collect();
+        let mut children: HashMap<Option<i32>, Vec<AccountBalance>> = HashMap::new();
+        for balance in balances {
+            let parent = balance
+                .account
+                .parent_id
+                .filter(|parent| ids.contains(parent));
+            children.entry(parent).or_default().push(balance);
+        }
+
+        let roots = children.remove(&None).unwrap_or_default();
+        roots
+            .into_iter()
+            .map(|balance| AccountTree::attach(balance, &mut children))
+            .collect()
+    }
+
+    fn attach(
+        balance: AccountBalance,
+        children: &mut HashMap<Option<i32>, Vec<AccountBalance>>,
+    ) -> AccountTree {
+        let own_children = match balance.account.id {
+            Some(id) => children.remove(&Some(id)).unwrap_or_default(),
+            None => vec![],
+        };
+        AccountTree {
+            children: own_children
+                .into_iter()
+                .map(|child| AccountTree::attach(child, children))
+                .collect(),
+            account: balance.account,
+            balance: balance.balance,
+            subtotal: balance.subtotal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FamilyBalances {
+    pub family: AccountFamily,
+    pub total: Money,
+    pub accounts: Vec<AccountBalance>,
+}
+
+impl FamilyBalances {
+    /// Groups account balances by family, in chart of accounts order.
+    /// Families without any account are left out.
+    pub fn group(balances: Vec<AccountBalance>) -> Vec<FamilyBalances> {
+        let mut groups: Vec<FamilyBalances> = AccountFamily::ALL
+            .iter()
+            .map(|family| FamilyBalances {
+                family: family.clone(),
+                total: Money::ZERO,
+                accounts: vec![],
+            })
+            .collect();
+
+        for balance in balances {
+            if let Some(group) = groups.iter_mut().find(|g| g.family == balance.account.family) {
+                group.total += balance.balance;
+                group.accounts.push(balance);
+            }
+        }
+
+        groups.retain(|group| !group.accounts.is_empty());
+        groups
+    }
+}
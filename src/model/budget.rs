@@ -0,0 +1,89 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::{Account, AccountFamily};
+use crate::model::money::Money;
+use crate::model::recurrence::Frequence;
+use crate::model::report::ReportPeriod;
+
+/// Amount planned for an Expense or Revenue account, and its sub-accounts, over
+/// every month or every year.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Budget {
+    /// Set by the server, ignored on input.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+
+    pub account: Account,
+    /// Planned for each period, spent on an Expense account or earned on a Revenue one.
+    pub amount: Money,
+    /// Monthly or Yearly, a single budget per account and frequence.
+    pub frequence: Frequence,
+}
+
+impl Budget {
+    /// Checks what the database cannot: the frequence and the account family.
+    pub fn check(&self) -> Result<(), String> {
+        if !matches!(self.frequence, Frequence::Monthly | Frequence::Yearly) {
+            return Err(format!(
+                "Budgets are Monthly or Yearly, not {:?}",
+                self.frequence
+            ));
+        }
+        if !matches!(
+            self.account.family,
+            AccountFamily::Expense | AccountFamily::Revenue
+        ) {
+            return Err(format!(
+                "Account '{}' is not an Expense or Revenue account",
+                self.account.reference()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Period of the budget containing `date`, see `ReportPeriod::containing`.
+    pub fn period(&self, date: NaiveDate) -> Option<ReportPeriod> {
+        ReportPeriod::containing(date, self.frequence)
+    }
+}
+
+/// Planned against actual amounts of a budget over one of its periods.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub period: ReportPeriod,
+    pub planned: Money,
+    /// Spent on the account and its sub-accounts, or earned for a Revenue budget.
+    pub actual: Money,
+    /// Planned minus actual, negative once exceeded.
+    pub remaining: Money,
+    /// Actual in percent of planned.
+    pub percent_used: f64,
+    /// More spent, or earned, than planned.
+    pub exceeded: bool,
+    /// More spent than planned, earning more than planned is no overspending.
+    pub overspent: bool,
+}
+
+impl BudgetStatus {
+    /// `balance` is the sum of the postings of the period, in the posting sign convention.
+    pub fn new(budget: Budget, period: ReportPeriod, balance: Money) -> BudgetStatus {
+        let actual = match budget.account.family {
+            AccountFamily::Revenue => -balance,
+            _ => balance,
+        };
+        let planned = budget.amount;
+        BudgetStatus {
+            period,
+            planned,
+            actual,
+            remaining: planned - actual,
+            percent_used: actual.percent_of(planned).unwrap_or_default(),
+            exceeded: actual > planned,
+            overspent: budget.account.family == AccountFamily::Expense && actual > planned,
+            budget,
+        }
+    }
+}
@@ -3,29 +3,74 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::model::account::Account;
+use crate::model::money::Money;
 
 use crate::utils::{datefmt_serialize, datefmt_deserialize};
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Entry {
+    /// Set by the server, ignored on input.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+
     pub description: String,
-    pub amount: f64,
+    pub amount: Money,
 
     #[serde(serialize_with = "datefmt_serialize", deserialize_with = "datefmt_deserialize")]
     pub event_date: DateTime<Utc>, 
 
     pub credit: Account,
     pub debit: Account,
+
+    /// Id of the entry this one cancels out, set on reversals only.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub reverses: Option<i32>,
 }
 
 impl Clone for Entry {
     fn clone(&self) -> Self {
         Entry {
+            id: self.id,
             description: self.description.clone(),
             amount: self.amount,
             event_date: self.event_date,
             credit: self.credit.clone(),
             debit: self.debit.clone(),
+            reverses: self.reverses,
+        }
+    }
+}
+
+/// One page of an entry listing.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EntryPage {
+    pub entries: Vec<Entry>,
+    /// Number of entries matching the search, all pages included
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Offset of the next page, missing on the last page
+    pub next_offset: Option<i64>,
+}
+
+impl EntryPage {
+    pub fn new(entries: Vec<Entry>, total: i64, limit: i64, offset: i64) -> Self {
+        EntryPage {
+            next_offset: next_offset(entries.len(), total, offset),
+            entries,
+            total,
+            limit,
+            offset,
         }
     }
 }
+
+/// Offset of the page after one of `len` items starting at `offset`, `None` on the last page.
+pub fn next_offset(len: usize, total: i64, offset: i64) -> Option<i64> {
+    let end = offset + len as i64;
+    if len > 0 && end < total {
+        Some(end)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::{Account, AccountFamily};
+use crate::model::money::Money;
+use crate::model::report::ReportPeriod;
+
+/// Money assigned to an envelope for a month, an Expense account and its sub-accounts.
+/// Nothing is posted: the money stays on the Asset accounts until entries spend it,
+/// the transfers only earmark it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EnvelopeTransfer {
+    /// Set by the server, ignored on input.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+
+    /// Any day of the month, stored as its first day.
+    pub month: NaiveDate,
+    /// Envelope the money is taken from, the to be budgeted pool when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Account>,
+    /// Envelope the money is assigned to, the to be budgeted pool when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<Account>,
+    pub amount: Money,
+}
+
+impl EnvelopeTransfer {
+    /// Checks the accounts, as recorded in the database.
+    pub fn check(&self) -> Result<(), String> {
+        let (from, to) = match (&self.from, &self.to) {
+            (None, None) => {
+                return Err("A transfer needs an envelope to take from or to assign to".into());
+            }
+            (Some(from), Some(to)) if from.id == to.id => {
+                return Err(format!(
+                    "Envelope '{}' cannot be transferred to itself",
+                    from.reference()
+                ));
+            }
+            (from, to) => (from, to),
+        };
+        for account in from.iter().chain(to) {
+            if account.family != AccountFamily::Expense {
+                return Err(format!(
+                    "Account '{}' is not an Expense account, only those can be envelopes",
+                    account.reference()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether one account is a sub-account of the other: their envelopes would both
+/// count the spending of the sub-account.
+pub fn nested(account: &Account, other: &Account) -> bool {
+    let is_under = |account: &Account, ancestor: &Account| {
+        let prefix = format!("{}{}", ancestor.reference(), Account::PATH_SEPARATOR);
+        account.reference().starts_with(&prefix)
+    };
+    is_under(account, other) || is_under(other, account)
+}
+
+/// Money held by an envelope over a month. Unspent money rolls over to the next month
+/// until swept back to the pool, overspending too until covered by a transfer.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Envelope {
+    pub account: Account,
+    /// Planned by the monthly budget of the account, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub planned: Option<Money>,
+    /// Available at the end of the previous month.
+    pub carried_over: Money,
+    /// Net transfers of the month.
+    pub assigned: Money,
+    /// Spent on the account and its sub-accounts during the month.
+    pub spent: Money,
+    /// Carried over, plus assigned, minus spent.
+    pub available: Money,
+    pub overspent: bool,
+}
+
+impl Envelope {
+    pub fn new(
+        account: Account,
+        planned: Option<Money>,
+        carried_over: Money,
+        assigned: Money,
+        spent: Money,
+    ) -> Envelope {
+        let available = carried_over + assigned - spent;
+        Envelope {
+            account,
+            planned,
+            carried_over,
+            assigned,
+            spent,
+            available,
+            overspent: available < Money::ZERO,
+        }
+    }
+}
+
+/// Envelopes at the end of a month, against the money on the Asset accounts.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EnvelopeMonth {
+    pub period: ReportPeriod,
+    /// Balance of the Asset accounts at the end of the month.
+    pub assets: Money,
+    /// Asset money held by no envelope, left to assign. Overspent envelopes hold
+    /// nothing, what they overspent has already left the assets.
+    pub to_be_budgeted: Money,
+    pub envelopes: Vec<Envelope>,
+}
+
+impl EnvelopeMonth {
+    pub fn new(period: ReportPeriod, assets: Money, envelopes: Vec<Envelope>) -> EnvelopeMonth {
+        let held: Money = envelopes
+            .iter()
+            .map(|envelope| envelope.available)
+            .filter(|available| *available > Money::ZERO)
+            .sum();
+        EnvelopeMonth {
+            period,
+            assets,
+            to_be_budgeted: assets - held,
+            envelopes,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::model::account::Account;
+use crate::model::money::Money;
+
+/// Change pushed to the `/events` stream, accounts are referred to by id.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum LedgerEvent {
+    AccountCreated { id: i32, account: Account },
+    EntryCreated { id: i32, postings: Vec<Movement> },
+    EntryUpdated { id: i32, postings: Vec<Movement> },
+    BalanceChanged { account: i32, balance: Money },
+}
+
+/// Posting of an entry event: positive when the account is credited.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Movement {
+    pub account: i32,
+    pub amount: Money,
+}
+
+impl LedgerEvent {
+    /// SSE event name, same as the `type` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerEvent::AccountCreated { .. } => "account-created",
+            LedgerEvent::EntryCreated { .. } => "entry-created",
+            LedgerEvent::EntryUpdated { .. } => "entry-updated",
+            LedgerEvent::BalanceChanged { .. } => "balance-changed",
+        }
+    }
+
+    /// Whether the event touches one of `accounts`, any event does when it is empty.
+    pub fn concerns(&self, accounts: &[i32]) -> bool {
+        if accounts.is_empty() {
+            return true;
+        }
+
+        match self {
+            LedgerEvent::AccountCreated { id, .. } => accounts.contains(id),
+            LedgerEvent::EntryCreated { postings, .. }
+            | LedgerEvent::EntryUpdated { postings, .. } => postings
+                .iter()
+                .any(|movement| accounts.contains(&movement.account)),
+            LedgerEvent::BalanceChanged { account, .. } => accounts.contains(account),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::Account;
+use crate::model::money::Money;
+
+use crate::utils::{datefmt_deserialize, datefmt_serialize};
+
+/// One entry seen from a single account: positive when the account is credited.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LedgerLine {
+    pub entry_id: i32,
+    pub description: String,
+
+    #[serde(serialize_with = "datefmt_serialize", deserialize_with = "datefmt_deserialize")]
+    pub event_date: DateTime<Utc>,
+
+    pub amount: Money,
+    /// Other side of the entry, its largest posting there for split entries.
+    pub counter_account: Account,
+    pub running_balance: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Ledger {
+    pub account: Account,
+    pub opening_balance: Money,
+    pub closing_balance: Money,
+    pub lines: Vec<LedgerLine>,
+}
+
+impl Ledger {
+    /// Builds the ledger from lines in chronological order, filling their running balance.
+    pub fn new(account: Account, opening_balance: Money, mut lines: Vec<LedgerLine>) -> Ledger {
+        let mut balance = opening_balance;
+        for line in lines.iter_mut() {
+            balance += line.amount;
+            line.running_balance = balance;
+        }
+
+        Ledger {
+            account,
+            opening_balance,
+            closing_balance: balance,
+            lines,
+        }
+    }
+}
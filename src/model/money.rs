@@ -0,0 +1,243 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use postgres_types::{FromSql, IsNull, ToSql, Type, to_sql_checked};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use utoipa::ToSchema;
+use utoipa::openapi::{ObjectBuilder, RefOr, Schema, SchemaFormat, SchemaType};
+
+/// Fractional digits kept by the `NUMERIC(20, 2)` amount columns.
+pub const SCALE: u32 = 2;
+
+/// Integer digits kept by the `NUMERIC(20, 2)` amount columns.
+const MAX_INTEGER_DIGITS: u32 = 18;
+
+/// Exact amount of money with at most two fractional digits.
+///
+/// Serialized as a decimal string (`"29.99"`) so no precision is lost on the way,
+/// deserialized from either a string or a JSON number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(Decimal);
+
+impl Money {
+    pub const ZERO: Money = Money(Decimal::ZERO);
+
+    pub fn from_cents(cents: i64) -> Self {
+        Money(Decimal::new(cents, SCALE))
+    }
+
+    /// Share of `whole` this amount makes, in percent rounded to one decimal.
+    /// `None` when `whole` is zero.
+    pub fn percent_of(self, whole: Money) -> Option<f64> {
+        if whole.0.is_zero() {
+            return None;
+        }
+        (self.0 * Decimal::ONE_HUNDRED / whole.0)
+            .round_dp(1)
+            .to_f64()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MoneyError {
+    Malformed(String),
+    TooManyFractionalDigits(String),
+    OutOfRange(String),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::Malformed(value) => write!(f, "'{}' is not a decimal amount", value),
+            MoneyError::TooManyFractionalDigits(value) => {
+                write!(f, "'{}' has more than {} fractional digits", value, SCALE)
+            }
+            MoneyError::OutOfRange(value) => {
+                write!(f, "'{}' has more than {} integer digits", value, MAX_INTEGER_DIGITS)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+impl TryFrom<Decimal> for Money {
+    type Error = MoneyError;
+
+    fn try_from(value: Decimal) -> Result<Self, Self::Error> {
+        let normalized = value.normalize();
+        if normalized.scale() > SCALE {
+            return Err(MoneyError::TooManyFractionalDigits(value.to_string()));
+        }
+        if normalized.abs() >= Decimal::from(10_i64.pow(MAX_INTEGER_DIGITS)) {
+            return Err(MoneyError::OutOfRange(value.to_string()));
+        }
+
+        let mut value = normalized;
+        value.rescale(SCALE);
+        Ok(Money(value))
+    }
+}
+
+impl From<Money> for Decimal {
+    fn from(value: Money) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = Decimal::from_str(s.trim()).map_err(|_| MoneyError::Malformed(s.to_string()))?;
+        Money::try_from(value)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}", self.0)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        // Decimal keeps the sign of zero, which would print as "-0.00"
+        if self.0.is_zero() {
+            return self;
+        }
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, |total, amount| total + amount)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl de::Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal amount with at most {} fractional digits", SCALE)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Money, E> {
+        value.parse().map_err(de::Error::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Money, E> {
+        Money::try_from(Decimal::from(value)).map_err(de::Error::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Money, E> {
+        Money::try_from(Decimal::from(value)).map_err(de::Error::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Money, E> {
+        // The shortest representation is what the client actually wrote, e.g. 29.99
+        self.visit_str(&value.to_string())
+    }
+}
+
+impl<'a> FromSql<'a> for Money {
+    fn from_sql(
+        ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let value = Decimal::from_sql(ty, raw)?;
+        Ok(Money::try_from(value)?)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <Decimal as FromSql>::accepts(ty)
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.0.to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <Decimal as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'s> ToSchema<'s> for Money {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "Money",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .format(Some(SchemaFormat::Custom("decimal".to_string())))
+                .pattern(Some(r"^-?\d{1,18}(\.\d{1,2})?$"))
+                .description(Some(
+                    "Exact amount with at most two fractional digits. Also accepted as a JSON number.",
+                ))
+                .example(Some(serde_json::json!("29.99")))
+                .into(),
+        )
+    }
+}
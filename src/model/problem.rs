@@ -0,0 +1,50 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Error body of every failed request, following RFC 7807 and served as
+/// `application/problem+json`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Problem {
+    /// URI reference identifying the problem type, `about:blank` when the status is enough.
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Short summary of the problem type, the reason phrase of the status.
+    pub title: String,
+    /// HTTP status code.
+    pub status: u16,
+    /// Explanation specific to this occurrence of the problem.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Every rejected input field, on validation problems only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// Input field rejected by a validation rule.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct FieldError {
+    /// Path of the field in the request body, such as `debit` or `postings[1].account`.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Problem {
+    pub fn new(status: u16, title: &str, detail: Option<String>) -> Self {
+        Problem {
+            problem_type: "about:blank".to_string(),
+            title: title.to_string(),
+            status,
+            detail,
+            errors: vec![],
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::Account;
+use crate::model::money::Money;
+
+/// Most occurrences of a recurrence posted at once, the next ones follow on the next runs.
+pub const MAX_DUE_OCCURRENCES: usize = 1000;
+
+/// Occurrences fall on the first day of each period: every day, every Monday,
+/// the 1st of every month or every January 1st.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Frequence {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequence {
+    /// First day of the period containing `date`.
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Frequence::Daily => date,
+            Frequence::Weekly => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Frequence::Monthly => date.with_day(1).unwrap_or(date),
+            Frequence::Yearly => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+
+    /// First day of the period following the one containing `date`.
+    pub fn next_period_start(&self, date: NaiveDate) -> NaiveDate {
+        let start = self.period_start(date);
+        match self {
+            Frequence::Daily => start + Days::new(1),
+            Frequence::Weekly => start + Days::new(7),
+            Frequence::Monthly => start + Months::new(1),
+            Frequence::Yearly => start + Months::new(12),
+        }
+    }
+
+    /// Same as `period_start`, `None` before the first representable date.
+    pub fn checked_period_start(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Frequence::Weekly => {
+                date.checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))
+            }
+            _ => Some(self.period_start(date)),
+        }
+    }
+
+    /// Same as `next_period_start`, `None` past the last representable date.
+    pub fn checked_next_period_start(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let start = self.checked_period_start(date)?;
+        match self {
+            Frequence::Daily => start.checked_add_days(Days::new(1)),
+            Frequence::Weekly => start.checked_add_days(Days::new(7)),
+            Frequence::Monthly => start.checked_add_months(Months::new(1)),
+            Frequence::Yearly => start.checked_add_months(Months::new(12)),
+        }
+    }
+}
+
+/// Template of an entry posted automatically at every period.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Recurrence {
+    /// Set by the server, ignored on input.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+
+    pub description: String,
+    pub amount: Money,
+    pub credit: Account,
+    pub debit: Account,
+
+    /// No occurrence before this day, occurrences start with the current period when missing.
+    pub start_date: Option<NaiveDate>,
+    /// No occurrence after this day (inclusive), endless when missing.
+    pub end_date: Option<NaiveDate>,
+
+    pub frequence: Frequence,
+}
+
+impl Recurrence {
+    /// Occurrence days due by `today`, after the `last` one already posted.
+    /// At most `MAX_DUE_OCCURRENCES`, none past the last representable date.
+    pub fn due_occurrences(&self, last: Option<NaiveDate>, today: NaiveDate) -> Vec<NaiveDate> {
+        let first = match (last, self.start_date) {
+            (Some(last), _) => self.frequence.checked_next_period_start(last),
+            (None, Some(start_date)) => match self.frequence.checked_period_start(start_date) {
+                Some(period_start) if period_start >= start_date => Some(period_start),
+                _ => self.frequence.checked_next_period_start(start_date),
+            },
+            (None, None) => self.frequence.checked_period_start(today),
+        };
+
+        let until = match self.end_date {
+            Some(end_date) => end_date.min(today),
+            None => today,
+        };
+
+        std::iter::successors(first, |day| self.frequence.checked_next_period_start(*day))
+            .take_while(|day| *day <= until)
+            .take(MAX_DUE_OCCURRENCES)
+            .collect()
+    }
+
+    /// Description of the entry posted for an occurrence, unique per occurrence.
+    pub fn occurrence_description(&self, occurrence: NaiveDate) -> String {
+        format!("{} {}", self.description, occurrence)
+    }
+}
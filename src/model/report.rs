@@ -0,0 +1,565 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::{Account, AccountFamily};
+use crate::model::balance::{AccountBalance, FamilyBalances};
+use crate::model::money::Money;
+use crate::model::recurrence::Frequence;
+
+use crate::utils::{datefmt_deserialize, datefmt_serialize};
+
+/// What is owned (assets) against what is owed (liabilities) and what is left to the
+/// owners (equity), at a date. Liability and equity amounts are negated so that what
+/// is owed reads positive, and assets total liabilities plus equity.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BalanceSheet {
+    /// Last instant of the entries taken into account.
+    #[serde(
+        serialize_with = "datefmt_serialize",
+        deserialize_with = "datefmt_deserialize"
+    )]
+    pub as_of: DateTime<Utc>,
+
+    pub assets: FamilyBalances,
+    pub liabilities: FamilyBalances,
+    /// Its total includes `net_income`.
+    pub equity: FamilyBalances,
+
+    /// Revenues minus expenses up to the date, no closing entry moves them to equity.
+    pub net_income: Money,
+    pub total_liabilities_and_equity: Money,
+}
+
+impl BalanceSheet {
+    /// Splits the balances, subtotals rolled up, into the sections of the sheet.
+    pub fn new(as_of: DateTime<Utc>, balances: &[AccountBalance]) -> BalanceSheet {
+        let net_income = -(family_total(balances, AccountFamily::Revenue)
+            + family_total(balances, AccountFamily::Expense));
+
+        let assets = section(balances, AccountFamily::Asset, false);
+        let liabilities = section(balances, AccountFamily::Liability, true);
+        let mut equity = section(balances, AccountFamily::Equity, true);
+        equity.total += net_income;
+
+        BalanceSheet {
+            as_of,
+            total_liabilities_and_equity: liabilities.total + equity.total,
+            assets,
+            liabilities,
+            equity,
+            net_income,
+        }
+    }
+}
+
+/// Sum of the own balances of the family accounts.
+fn family_total(balances: &[AccountBalance], family: AccountFamily) -> Money {
+    balances
+        .iter()
+        .filter(|balance| balance.account.family == family)
+        .map(|balance| balance.balance)
+        .sum()
+}
+
+/// Balances of the family accounts, kept even when there are none.
+fn section(balances: &[AccountBalance], family: AccountFamily, negate: bool) -> FamilyBalances {
+    let accounts: Vec<AccountBalance> = balances
+        .iter()
+        .filter(|balance| balance.account.family == family)
+        .map(|balance| match negate {
+            true => AccountBalance {
+                account: balance.account.clone(),
+                balance: -balance.balance,
+                subtotal: -balance.subtotal,
+            },
+            false => balance.clone(),
+        })
+        .collect();
+
+    FamilyBalances {
+        family,
+        total: accounts.iter().map(|balance| balance.balance).sum(),
+        accounts,
+    }
+}
+
+/// Column of a report, both days inclusive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReportPeriod {
+    pub label: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+impl ReportPeriod {
+    /// Splits the days from `start_date` to `end_date`, both inclusive, along calendar
+    /// periods. The first and last periods may be partial, no grouping keeps a single one.
+    /// Periods are only built as they are consumed.
+    pub fn split(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        grouping: Option<Frequence>,
+    ) -> impl Iterator<Item = ReportPeriod> {
+        let mut period_start = Some(start_date);
+        std::iter::from_fn(move || {
+            let start = period_start.filter(|start| *start <= end_date)?;
+            let Some(grouping) = grouping else {
+                period_start = None;
+                return Some(ReportPeriod {
+                    label: format!("{} to {}", start_date, end_date),
+                    start_date,
+                    end_date,
+                });
+            };
+
+            let next_start = grouping.next_period_start(start);
+            period_start = Some(next_start);
+            Some(ReportPeriod {
+                label: period_label(grouping, start),
+                start_date: start,
+                end_date: (next_start - Days::new(1)).min(end_date),
+            })
+        })
+    }
+
+    /// The whole calendar period containing `date`, `None` when it ends past the
+    /// last representable date.
+    pub fn containing(date: NaiveDate, grouping: Frequence) -> Option<ReportPeriod> {
+        let next_start = grouping.checked_next_period_start(date)?;
+        Some(ReportPeriod {
+            label: period_label(grouping, date),
+            start_date: grouping.period_start(date),
+            end_date: next_start - Days::new(1),
+        })
+    }
+}
+
+fn period_label(grouping: Frequence, date: NaiveDate) -> String {
+    match grouping {
+        Frequence::Daily => date.format("%Y-%m-%d").to_string(),
+        Frequence::Weekly => date.format("%G-W%V").to_string(),
+        Frequence::Monthly => date.format("%Y-%m").to_string(),
+        Frequence::Yearly => date.format("%Y").to_string(),
+    }
+}
+
+/// Amounts of an account over each period of a report, then over all of them.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StatementLine {
+    pub account: Account,
+    pub amounts: Vec<Money>,
+    pub total: Money,
+    /// Amounts of the account and of all its sub-accounts, over each period.
+    pub subtotals: Vec<Money>,
+    pub subtotal: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StatementSection {
+    pub family: AccountFamily,
+    /// Accounts with movements over the report, sub-accounts included.
+    pub lines: Vec<StatementLine>,
+    pub totals: Vec<Money>,
+    pub total: Money,
+}
+
+/// Rows of strings, amounts included, ready to display as they are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReportTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Revenues and expenses of each period, and the net income they leave.
+/// Revenues are negated so that money earned reads positive.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IncomeStatement {
+    pub periods: Vec<ReportPeriod>,
+    pub revenues: StatementSection,
+    pub expenses: StatementSection,
+    /// Revenues minus expenses, over each period.
+    pub net_income: Vec<Money>,
+    pub total_net_income: Money,
+    /// The same figures, one row per account then totals, one column per period.
+    pub table: ReportTable,
+}
+
+impl IncomeStatement {
+    /// `balances` holds the balances of every account over each period, in period
+    /// order, subtotals rolled up.
+    pub fn new(periods: Vec<ReportPeriod>, balances: &[Vec<AccountBalance>]) -> IncomeStatement {
+        let revenues = statement_section(balances, AccountFamily::Revenue, true);
+        let expenses = statement_section(balances, AccountFamily::Expense, false);
+        let net_income: Vec<Money> = revenues
+            .totals
+            .iter()
+            .zip(&expenses.totals)
+            .map(|(revenue, expense)| *revenue - *expense)
+            .collect();
+        let total_net_income = revenues.total - expenses.total;
+
+        let mut columns = vec!["Account".to_string()];
+        columns.extend(periods.iter().map(|period| period.label.clone()));
+        columns.push("Total".to_string());
+
+        let mut rows = vec![];
+        for section in [&revenues, &expenses] {
+            for line in &section.lines {
+                rows.push(table_row(
+                    line.account.reference(),
+                    &line.subtotals,
+                    line.subtotal,
+                ));
+            }
+            let label = format!("Total {:?}", section.family);
+            rows.push(table_row(&label, &section.totals, section.total));
+        }
+        rows.push(table_row("Net income", &net_income, total_net_income));
+
+        IncomeStatement {
+            periods,
+            revenues,
+            expenses,
+            net_income,
+            total_net_income,
+            table: ReportTable { columns, rows },
+        }
+    }
+}
+
+/// Lines of the family accounts moved over any period, `balances` being per period.
+fn statement_section(
+    balances: &[Vec<AccountBalance>],
+    family: AccountFamily,
+    negate: bool,
+) -> StatementSection {
+    let sign = |amount: Money| if negate { -amount } else { amount };
+
+    let accounts: Vec<&Account> = balances
+        .first()
+        .map(|first| {
+            first
+                .iter()
+                .filter(|balance| balance.account.family == family)
+                .map(|balance| &balance.account)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut lines = vec![];
+    for account in accounts {
+        let period_balances: Vec<Option<&AccountBalance>> = balances
+            .iter()
+            .map(|period| {
+                period
+                    .iter()
+                    .find(|balance| balance.account.id == account.id)
+            })
+            .collect();
+        let amounts: Vec<Money> = period_balances
+            .iter()
+            .map(|balance| balance.map_or(Money::ZERO, |balance| sign(balance.balance)))
+            .collect();
+        let subtotals: Vec<Money> = period_balances
+            .iter()
+            .map(|balance| balance.map_or(Money::ZERO, |balance| sign(balance.subtotal)))
+            .collect();
+
+        let unmoved = |amounts: &[Money]| amounts.iter().all(|amount| *amount == Money::ZERO);
+        if unmoved(&amounts) && unmoved(&subtotals) {
+            continue;
+        }
+        lines.push(StatementLine {
+            account: account.clone(),
+            total: amounts.iter().copied().sum(),
+            amounts,
+            subtotal: subtotals.iter().copied().sum(),
+            subtotals,
+        });
+    }
+
+    let totals: Vec<Money> = (0..balances.len())
+        .map(|period| lines.iter().map(|line| line.amounts[period]).sum())
+        .collect();
+    StatementSection {
+        family,
+        total: totals.iter().copied().sum(),
+        lines,
+        totals,
+    }
+}
+
+fn table_row(label: &str, amounts: &[Money], total: Money) -> Vec<String> {
+    let mut row = vec![label.to_string()];
+    row.extend(amounts.iter().map(|amount| amount.to_string()));
+    row.push(total.to_string());
+    row
+}
+
+/// Amounts debited from and credited to an account, both positive.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrialBalanceLine {
+    pub account: Account,
+    pub debits: Money,
+    pub credits: Money,
+    /// Credits minus debits.
+    pub balance: Money,
+}
+
+/// Account whose materialized balance disagrees with its postings.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BalanceMismatch {
+    pub account: Account,
+    /// Balance read from the `account_balances` view, none when the account is missing there.
+    pub recorded: Option<Money>,
+    /// Sum of the account postings.
+    pub computed: Money,
+}
+
+/// Debits and credits of every account at a date. Every entry balances, so the grand
+/// totals match unless the database was changed behind the back of its triggers.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrialBalance {
+    /// Last instant of the entries taken into account.
+    #[serde(
+        serialize_with = "datefmt_serialize",
+        deserialize_with = "datefmt_deserialize"
+    )]
+    pub as_of: DateTime<Utc>,
+
+    pub lines: Vec<TrialBalanceLine>,
+    pub total_debits: Money,
+    pub total_credits: Money,
+    /// Whether the total debits equal the total credits.
+    pub balanced: bool,
+
+    /// Accounts whose materialized balance is stale, checked over all entries
+    /// whatever `as_of`.
+    pub mismatches: Vec<BalanceMismatch>,
+}
+
+impl TrialBalance {
+    pub fn new(
+        as_of: DateTime<Utc>,
+        lines: Vec<TrialBalanceLine>,
+        mismatches: Vec<BalanceMismatch>,
+    ) -> TrialBalance {
+        let total_debits: Money = lines.iter().map(|line| line.debits).sum();
+        let total_credits: Money = lines.iter().map(|line| line.credits).sum();
+
+        TrialBalance {
+            as_of,
+            lines,
+            total_debits,
+            total_credits,
+            balanced: total_debits == total_credits,
+            mismatches,
+        }
+    }
+}
+
+/// Money a counter-account brought into the cash accounts, and took out of them.
+#[derive(Debug, Clone)]
+pub struct CashFlow {
+    pub account: Account,
+    pub inflow: Money,
+    pub outflow: Money,
+}
+
+/// Inflows and outflows of a counter-account over each period, both positive.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CashFlowLine {
+    pub account: Account,
+    pub inflows: Vec<Money>,
+    pub outflows: Vec<Money>,
+    pub total_inflows: Money,
+    pub total_outflows: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CashFlowSection {
+    pub family: AccountFamily,
+    pub lines: Vec<CashFlowLine>,
+    pub inflows: Vec<Money>,
+    pub outflows: Vec<Money>,
+}
+
+/// Where the money entering and leaving a set of asset accounts came from and went
+/// to, counter-accounts grouped by family. Transfers between the followed accounts
+/// are left out.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CashFlowStatement {
+    /// Followed accounts, sub-accounts included.
+    pub accounts: Vec<Account>,
+    pub periods: Vec<ReportPeriod>,
+    /// Families without any counter-account are left out.
+    pub families: Vec<CashFlowSection>,
+    pub inflows: Vec<Money>,
+    pub outflows: Vec<Money>,
+    /// Inflows minus outflows, the change of the followed accounts balance over each period.
+    pub net_change: Vec<Money>,
+    pub total_net_change: Money,
+}
+
+impl CashFlowStatement {
+    /// `flows` holds the cash flows of the counter-accounts over each period, in period order.
+    pub fn new(
+        accounts: Vec<Account>,
+        periods: Vec<ReportPeriod>,
+        flows: &[Vec<CashFlow>],
+    ) -> CashFlowStatement {
+        let mut counter_accounts: Vec<&Account> =
+            flows.iter().flatten().map(|flow| &flow.account).collect();
+        counter_accounts.sort_by_key(|account| account.id);
+        counter_accounts.dedup_by_key(|account| account.id);
+
+        let lines: Vec<CashFlowLine> = counter_accounts
+            .into_iter()
+            .map(|account| {
+                let period_flows: Vec<Option<&CashFlow>> = flows
+                    .iter()
+                    .map(|period| period.iter().find(|flow| flow.account.id == account.id))
+                    .collect();
+                let inflows: Vec<Money> = period_flows
+                    .iter()
+                    .map(|flow| flow.map_or(Money::ZERO, |flow| flow.inflow))
+                    .collect();
+                let outflows: Vec<Money> = period_flows
+                    .iter()
+                    .map(|flow| flow.map_or(Money::ZERO, |flow| flow.outflow))
+                    .collect();
+                CashFlowLine {
+                    account: account.clone(),
+                    total_inflows: inflows.iter().copied().sum(),
+                    total_outflows: outflows.iter().copied().sum(),
+                    inflows,
+                    outflows,
+                }
+            })
+            .collect();
+
+        let per_period = |lines: &[&CashFlowLine], flows: fn(&CashFlowLine) -> &Vec<Money>| {
+            (0..periods.len())
+                .map(|period| lines.iter().map(|line| flows(line)[period]).sum())
+                .collect::<Vec<Money>>()
+        };
+
+        let mut families = vec![];
+        for family in AccountFamily::ALL {
+            let family_lines: Vec<&CashFlowLine> = lines
+                .iter()
+                .filter(|line| line.account.family == family)
+                .collect();
+            if family_lines.is_empty() {
+                continue;
+            }
+            families.push(CashFlowSection {
+                family,
+                inflows: per_period(&family_lines, |line| &line.inflows),
+                outflows: per_period(&family_lines, |line| &line.outflows),
+                lines: family_lines.into_iter().cloned().collect(),
+            });
+        }
+
+        let all_lines: Vec<&CashFlowLine> = lines.iter().collect();
+        let inflows = per_period(&all_lines, |line| &line.inflows);
+        let outflows = per_period(&all_lines, |line| &line.outflows);
+        let net_change: Vec<Money> = inflows
+            .iter()
+            .zip(&outflows)
+            .map(|(inflow, outflow)| *inflow - *outflow)
+            .collect();
+
+        CashFlowStatement {
+            accounts,
+            total_net_change: net_change.iter().copied().sum(),
+            periods,
+            families,
+            inflows,
+            outflows,
+            net_change,
+        }
+    }
+}
+
+/// Postings of a family over a report period, in the posting sign convention.
+#[derive(Debug, Clone)]
+pub struct FamilyMovement {
+    /// Index of the period in the report.
+    pub period: usize,
+    pub family: AccountFamily,
+    pub amount: Money,
+}
+
+/// Balance of a family, negated for liabilities, equity and revenues so that it reads
+/// positive like in the other reports.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FamilyTotal {
+    pub family: AccountFamily,
+    pub total: Money,
+}
+
+/// Balances at the end of a period, every entry since the first one included.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NetWorthPoint {
+    pub period: ReportPeriod,
+    pub assets: Money,
+    pub liabilities: Money,
+    /// Assets minus liabilities.
+    pub net_worth: Money,
+    /// Every family, in chart of accounts order.
+    pub families: Vec<FamilyTotal>,
+}
+
+/// Net worth at the end of each period of a report.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NetWorthSeries {
+    pub points: Vec<NetWorthPoint>,
+}
+
+impl NetWorthSeries {
+    /// Accumulates the movements, those of the first period including every earlier posting.
+    pub fn new(periods: Vec<ReportPeriod>, movements: &[FamilyMovement]) -> NetWorthSeries {
+        let mut balances = [Money::ZERO; AccountFamily::ALL.len()];
+        let mut points = vec![];
+        for (index, period) in periods.into_iter().enumerate() {
+            for movement in movements.iter().filter(|movement| movement.period == index) {
+                if let Some(position) = AccountFamily::ALL
+                    .iter()
+                    .position(|family| *family == movement.family)
+                {
+                    balances[position] += movement.amount;
+                }
+            }
+
+            let families: Vec<FamilyTotal> = AccountFamily::ALL
+                .into_iter()
+                .zip(balances)
+                .map(|(family, balance)| FamilyTotal {
+                    total: match family {
+                        AccountFamily::Asset | AccountFamily::Expense => balance,
+                        _ => -balance,
+                    },
+                    family,
+                })
+                .collect();
+            let total = |family: AccountFamily| {
+                families
+                    .iter()
+                    .find(|total| total.family == family)
+                    .map_or(Money::ZERO, |total| total.total)
+            };
+            let assets = total(AccountFamily::Asset);
+            let liabilities = total(AccountFamily::Liability);
+            points.push(NetWorthPoint {
+                period,
+                assets,
+                liabilities,
+                net_worth: assets - liabilities,
+                families,
+            });
+        }
+        NetWorthSeries { points }
+    }
+}
@@ -4,17 +4,49 @@ mod tests {
     use chrono::Utc;
 
     use crate::model::account::*;
+    use crate::model::balance::*;
+    use crate::model::budget::*;
     use crate::model::entry::*;
+    use crate::model::envelope::*;
+    use crate::model::event::*;
+    use crate::model::ledger::*;
+    use crate::model::money::*;
+    use crate::model::recurrence::*;
+    use crate::model::report::*;
+    use crate::model::transaction::*;
+
+    fn money(value: &str) -> Money {
+        value.parse().unwrap()
+    }
+
+    /// Top-level account with no sub-account, named after its id.
+    fn account_balance(id: i32, family: AccountFamily, balance: &str) -> AccountBalance {
+        AccountBalance {
+            account: Account {
+                id: Some(id),
+                name: format!("Account {}", id),
+                family,
+                parent_id: None,
+                path: None,
+            },
+            balance: money(balance),
+            subtotal: money(balance),
+        }
+    }
 
     #[test]
     fn test_serialization() {
 
         let account = Account {
+            id: Some(3),
             name: "Test Account".to_string(),
             family: AccountFamily::Asset,
+            parent_id: None,
+            path: None,
         };
 
         let expected_account_json = serde_json::json!({
+            "id": 3,
             "name": "Test Account",
             "family": "Asset"
         });
@@ -22,31 +54,43 @@ mod tests {
         let account_json = serde_json::json!(&account);
 
         assert_eq!(expected_account_json, account_json);
+        let account: Account = serde_json::from_value(account_json).unwrap();
+        assert_eq!(account.id, None);
+        assert_eq!("expense".parse(), Ok(AccountFamily::Expense));
+        assert!("Income".parse::<AccountFamily>().is_err());
 
 
         let credit = Account {
+            id: None,
             name: "Credit Account".to_string(),
             family: AccountFamily::Liability,
+            parent_id: None,
+            path: None,
         };
 
         let debit = Account {
+            id: None,
             name: "Debit Account".to_string(),
             family: AccountFamily::Expense,
+            parent_id: None,
+            path: None,
         };
 
         let entry = Entry {
+            id: None,
             description: "Test Entry".to_string(),
-            amount: 100.0,
+            amount: money("100"),
             event_date: DateTime::parse_from_rfc3339("2023-10-01T12:00:00.000Z")
                 .unwrap()
                 .with_timezone(&Utc), 
-            credit: credit,
-            debit: debit,
+            credit,
+            debit,
+            reverses: None,
         };
 
         let expected_entry_json = serde_json::json!({
             "description": "Test Entry",
-            "amount": 100.0,
+            "amount": "100.00",
             "event_date": "2023-10-01T12:00:00Z",
             "credit": {
                 "name": "Credit Account",
@@ -62,4 +106,732 @@ mod tests {
 
         assert_eq!(expected_entry_json, entry_json);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_balances_grouped_by_family() {
+        let balance = |name: &str, family: AccountFamily, balance: &str| AccountBalance {
+            account: Account {
+                id: None,
+                name: name.to_string(),
+                family,
+                parent_id: None,
+                path: None,
+            },
+            balance: money(balance),
+            subtotal: money(balance),
+        };
+
+        let groups = FamilyBalances::group(vec![
+            balance("Rent", AccountFamily::Expense, "1000"),
+            balance("Bank", AccountFamily::Asset, "5641"),
+            balance("Salary", AccountFamily::Revenue, "-10000"),
+            balance("Insurance", AccountFamily::Expense, "200"),
+        ]);
+
+        let families: Vec<AccountFamily> = groups.iter().map(|g| g.family.clone()).collect();
+        assert_eq!(
+            families,
+            vec![
+                AccountFamily::Asset,
+                AccountFamily::Revenue,
+                AccountFamily::Expense
+            ]
+        );
+        assert_eq!(groups[2].total, money("1200"));
+        assert_eq!(groups[2].accounts.len(), 2);
+        assert_eq!(groups[1].total, money("-10000"));
+    }
+
+    #[test]
+    fn test_account_tree_rollup() {
+        let balance = |id: i32, parent_id: Option<i32>, balance: &str| AccountBalance {
+            account: Account {
+                id: Some(id),
+                name: format!("Account {}", id),
+                family: AccountFamily::Expense,
+                parent_id,
+                path: None,
+            },
+            balance: money(balance),
+            subtotal: Money::ZERO,
+        };
+        // Housing (1) > Rent (2), Utilities (3) > Electricity (4); Food (5)
+        let balances = vec![
+            balance(4, Some(3), "45"),
+            balance(1, None, "0"),
+            balance(2, Some(1), "1000"),
+            balance(3, Some(1), "20"),
+            balance(5, None, "300"),
+        ];
+
+        let mut rolled_up = balances.clone();
+        AccountBalance::roll_up(&mut rolled_up);
+        let subtotals: Vec<Money> = rolled_up.iter().map(|b| b.subtotal).collect();
+        assert_eq!(
+            subtotals,
+            vec![
+                money("45"),
+                money("1065"),
+                money("1000"),
+                money("65"),
+                money("300")
+            ]
+        );
+
+        let tree = AccountTree::build(balances);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].account.id, Some(1));
+        assert_eq!(tree[0].subtotal, money("1065"));
+        let children: Vec<Option<i32>> = tree[0].children.iter().map(|c| c.account.id).collect();
+        assert_eq!(children, vec![Some(2), Some(3)]);
+        assert_eq!(tree[0].children[1].children[0].account.id, Some(4));
+        assert_eq!(tree[0].children[1].subtotal, money("65"));
+        assert!(tree[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_balance_sheet_balances() {
+        // Salary received on the bank, part of it spent, a loan not paid back yet
+        let balances = vec![
+            account_balance(1, AccountFamily::Asset, "6500"),
+            account_balance(2, AccountFamily::Revenue, "-3000"),
+            account_balance(3, AccountFamily::Expense, "1200"),
+            account_balance(4, AccountFamily::Liability, "-4000"),
+            account_balance(5, AccountFamily::Equity, "-700"),
+        ];
+
+        let as_of = Utc::now();
+        let sheet = BalanceSheet::new(as_of, &balances);
+        assert_eq!(sheet.assets.total, money("6500"));
+        assert_eq!(sheet.liabilities.total, money("4000"));
+        assert_eq!(sheet.liabilities.accounts[0].subtotal, money("4000"));
+        assert_eq!(sheet.net_income, money("1800"));
+        assert_eq!(sheet.equity.total, money("2500"));
+        assert_eq!(sheet.equity.accounts.len(), 1);
+        assert_eq!(sheet.total_liabilities_and_equity, sheet.assets.total);
+
+        let empty = BalanceSheet::new(as_of, &[]);
+        assert_eq!(empty.assets.family, AccountFamily::Asset);
+        assert_eq!(empty.total_liabilities_and_equity, Money::ZERO);
+    }
+
+    #[test]
+    fn test_report_periods_split() {
+        let months: Vec<_> = ReportPeriod::split(
+            day("2024-11-15"),
+            day("2025-01-10"),
+            Some(Frequence::Monthly),
+        )
+        .collect();
+        let labels: Vec<&str> = months.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["2024-11", "2024-12", "2025-01"]);
+        assert_eq!(months[0].start_date, day("2024-11-15"));
+        assert_eq!(months[0].end_date, day("2024-11-30"));
+        assert_eq!(months[2].start_date, day("2025-01-01"));
+        assert_eq!(months[2].end_date, day("2025-01-10"));
+
+        let weeks: Vec<_> = ReportPeriod::split(
+            day("2024-12-30"),
+            day("2025-01-06"),
+            Some(Frequence::Weekly),
+        )
+        .collect();
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[0].label, "2025-W01");
+
+        let whole: Vec<_> =
+            ReportPeriod::split(day("2024-01-01"), day("2024-12-31"), None).collect();
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].end_date, day("2024-12-31"));
+
+        // Lazy, a range of millions of days is never built at once
+        let days = ReportPeriod::split(
+            day("0001-01-01"),
+            day("9999-12-31"),
+            Some(Frequence::Daily),
+        );
+        assert_eq!(days.take(3).last().unwrap().start_date, day("0001-01-03"));
+    }
+
+    #[test]
+    fn test_income_statement_per_period() {
+        let period = |label: &str| ReportPeriod {
+            label: label.to_string(),
+            start_date: Utc::now().date_naive(),
+            end_date: Utc::now().date_naive(),
+        };
+        let balances = vec![
+            vec![
+                account_balance(1, AccountFamily::Asset, "2000"),
+                account_balance(2, AccountFamily::Revenue, "-3000"),
+                account_balance(3, AccountFamily::Expense, "1000"),
+                account_balance(4, AccountFamily::Expense, "0"),
+            ],
+            vec![
+                account_balance(1, AccountFamily::Asset, "-500"),
+                account_balance(2, AccountFamily::Revenue, "0"),
+                account_balance(3, AccountFamily::Expense, "500"),
+                account_balance(4, AccountFamily::Expense, "0"),
+            ],
+        ];
+
+        let statement = IncomeStatement::new(vec![period("2024-11"), period("2024-12")], &balances);
+        assert_eq!(statement.revenues.totals, vec![money("3000"), Money::ZERO]);
+        assert_eq!(statement.expenses.lines.len(), 1);
+        assert_eq!(statement.expenses.total, money("1500"));
+        assert_eq!(statement.net_income, vec![money("2000"), money("-500")]);
+        assert_eq!(statement.total_net_income, money("1500"));
+
+        assert_eq!(
+            statement.table.columns,
+            vec!["Account", "2024-11", "2024-12", "Total"]
+        );
+        assert_eq!(
+            statement.table.rows.last().unwrap(),
+            &vec!["Net income", "2000.00", "-500.00", "1500.00"]
+        );
+        assert_eq!(statement.table.rows.len(), 5);
+    }
+
+    #[test]
+    fn test_trial_balance_totals() {
+        let line = |id: i32, debits: &str, credits: &str| TrialBalanceLine {
+            account: Account {
+                id: Some(id),
+                name: format!("Account {}", id),
+                family: AccountFamily::Asset,
+                parent_id: None,
+                path: None,
+            },
+            debits: money(debits),
+            credits: money(credits),
+            balance: money(credits) - money(debits),
+        };
+
+        let as_of = Utc::now();
+        let trial_balance = TrialBalance::new(
+            as_of,
+            vec![line(1, "1000", "5000"), line(2, "5000", "0"), line(3, "0", "1000")],
+            vec![],
+        );
+        assert_eq!(trial_balance.total_debits, money("6000"));
+        assert_eq!(trial_balance.total_credits, money("6000"));
+        assert!(trial_balance.balanced);
+
+        let trial_balance = TrialBalance::new(as_of, vec![line(1, "10", "0")], vec![]);
+        assert!(!trial_balance.balanced);
+    }
+
+    #[test]
+    fn test_cash_flow_by_counter_account() {
+        let account = |id: i32, family: AccountFamily| Account {
+            id: Some(id),
+            name: format!("Account {}", id),
+            family,
+            parent_id: None,
+            path: None,
+        };
+        let flow = |id: i32, family: AccountFamily, inflow: &str, outflow: &str| CashFlow {
+            account: account(id, family),
+            inflow: money(inflow),
+            outflow: money(outflow),
+        };
+
+        let periods: Vec<_> = ReportPeriod::split(
+            day("2024-11-01"),
+            day("2024-12-31"),
+            Some(Frequence::Monthly),
+        )
+        .collect();
+        let statement = CashFlowStatement::new(
+            vec![account(1, AccountFamily::Asset)],
+            periods,
+            &[
+                vec![
+                    flow(5, AccountFamily::Expense, "0", "800"),
+                    flow(9, AccountFamily::Revenue, "3000", "0"),
+                ],
+                vec![
+                    flow(3, AccountFamily::Expense, "10", "60"),
+                    flow(5, AccountFamily::Expense, "0", "800"),
+                ],
+            ],
+        );
+
+        let families: Vec<&AccountFamily> = statement.families.iter().map(|f| &f.family).collect();
+        assert_eq!(families, vec![&AccountFamily::Revenue, &AccountFamily::Expense]);
+        let expenses = &statement.families[1];
+        assert_eq!(expenses.lines.len(), 2);
+        assert_eq!(expenses.lines[0].inflows, vec![money("0"), money("10")]);
+        assert_eq!(expenses.lines[1].total_outflows, money("1600"));
+        assert_eq!(expenses.outflows, vec![money("800"), money("860")]);
+
+        assert_eq!(statement.inflows, vec![money("3000"), money("10")]);
+        assert_eq!(statement.net_change, vec![money("2200"), money("-850")]);
+        assert_eq!(statement.total_net_change, money("1350"));
+    }
+
+    #[test]
+    fn test_net_worth_accumulates_periods() {
+        let movement = |period: usize, family: AccountFamily, amount: &str| FamilyMovement {
+            period,
+            family,
+            amount: money(amount),
+        };
+
+        let periods: Vec<_> = ReportPeriod::split(
+            day("2024-10-01"),
+            day("2024-12-31"),
+            Some(Frequence::Monthly),
+        )
+        .collect();
+        let series = NetWorthSeries::new(
+            periods,
+            &[
+                movement(0, AccountFamily::Asset, "1000"),
+                movement(0, AccountFamily::Equity, "-1000"),
+                movement(2, AccountFamily::Asset, "-300"),
+                movement(2, AccountFamily::Liability, "-200"),
+                movement(2, AccountFamily::Expense, "500"),
+            ],
+        );
+
+        let net_worth: Vec<Money> = series.points.iter().map(|p| p.net_worth).collect();
+        assert_eq!(net_worth, vec![money("1000"), money("1000"), money("500")]);
+        let last = &series.points[2];
+        assert_eq!(last.period.label, "2024-12");
+        assert_eq!(last.assets, money("700"));
+        assert_eq!(last.liabilities, money("200"));
+        assert_eq!(last.families[2].total, money("1000"));
+        assert_eq!(last.families[4].total, money("500"));
+    }
+
+    #[test]
+    fn test_budget_status() {
+        let budget = |family: AccountFamily, frequence: Frequence| Budget {
+            id: Some(1),
+            account: Account {
+                id: Some(1),
+                name: "Food".to_string(),
+                family,
+                parent_id: None,
+                path: None,
+            },
+            amount: money("400"),
+            frequence,
+        };
+
+        let groceries = budget(AccountFamily::Expense, Frequence::Monthly);
+        assert_eq!(groceries.check(), Ok(()));
+        let period = groceries.period(day("2024-02-10")).unwrap();
+        assert_eq!(period.label, "2024-02");
+        assert_eq!(period.start_date, day("2024-02-01"));
+        assert_eq!(period.end_date, day("2024-02-29"));
+
+        let status = BudgetStatus::new(groceries.clone(), period.clone(), money("100"));
+        assert_eq!(status.remaining, money("300"));
+        assert_eq!(status.percent_used, 25.0);
+        assert!(!status.exceeded);
+        assert!(!status.overspent);
+        let status = BudgetStatus::new(groceries, period.clone(), money("450.50"));
+        assert_eq!(status.remaining, money("-50.50"));
+        assert_eq!(status.percent_used, 112.6);
+        assert!(status.exceeded);
+        assert!(status.overspent);
+
+        // Revenues are credited, their postings are negative
+        let salary = budget(AccountFamily::Revenue, Frequence::Yearly);
+        assert_eq!(salary.period(day("2024-02-10")).unwrap().label, "2024");
+        assert!(salary.period(chrono::NaiveDate::MAX).is_none());
+        let status = BudgetStatus::new(salary.clone(), period.clone(), money("-100"));
+        assert_eq!(status.actual, money("100"));
+        let status = BudgetStatus::new(salary, period, money("-500"));
+        assert!(status.exceeded);
+        assert!(!status.overspent);
+
+        assert!(budget(AccountFamily::Asset, Frequence::Monthly).check().is_err());
+        assert!(budget(AccountFamily::Expense, Frequence::Weekly).check().is_err());
+    }
+
+    #[test]
+    fn test_envelopes_roll_over() {
+        let account = |id: i32, path: &str, family: AccountFamily| Account {
+            id: Some(id),
+            name: path.rsplit(Account::PATH_SEPARATOR).next().unwrap().to_string(),
+            family,
+            parent_id: None,
+            path: Some(path.to_string()),
+        };
+        let food = account(1, "Food", AccountFamily::Expense);
+        let restaurant = account(2, "Food:Restaurant", AccountFamily::Expense);
+        let fun = account(3, "Fun", AccountFamily::Expense);
+        let bank = account(4, "Bank", AccountFamily::Asset);
+
+        let transfer = |from: Option<&Account>, to: Option<&Account>| EnvelopeTransfer {
+            id: None,
+            month: day("2024-12-01"),
+            from: from.cloned(),
+            to: to.cloned(),
+            amount: money("50"),
+        };
+        assert_eq!(transfer(None, Some(&food)).check(), Ok(()));
+        assert_eq!(transfer(Some(&food), Some(&fun)).check(), Ok(()));
+        assert!(transfer(None, None).check().is_err());
+        assert!(transfer(Some(&food), Some(&food)).check().is_err());
+        assert!(transfer(None, Some(&bank)).check().is_err());
+
+        assert!(nested(&restaurant, &food));
+        assert!(nested(&food, &restaurant));
+        assert!(!nested(&food, &fun));
+        assert!(!nested(&food, &account(5, "Foodstuff", AccountFamily::Expense)));
+
+        let period = ReportPeriod::containing(day("2024-12-15"), Frequence::Monthly).unwrap();
+        let overspent = Envelope::new(fun.clone(), None, money("0"), money("10"), money("30"));
+        let month = EnvelopeMonth::new(period.clone(), money("1000"), vec![overspent]);
+        assert_eq!(month.to_be_budgeted, money("1000"));
+
+        let month = EnvelopeMonth::new(
+            period,
+            money("1000"),
+            vec![
+                Envelope::new(food, None, money("20"), money("300"), money("250")),
+                Envelope::new(fun, Some(money("40")), money("0"), money("40"), money("60")),
+            ],
+        );
+        assert_eq!(month.period.end_date, day("2024-12-31"));
+        assert_eq!(month.envelopes[0].available, money("70"));
+        assert!(!month.envelopes[0].overspent);
+        assert_eq!(month.envelopes[1].available, money("-20"));
+        assert!(month.envelopes[1].overspent);
+        // The overspent envelope does not give its 20 back to the pool
+        assert_eq!(month.to_be_budgeted, money("930"));
+    }
+
+    #[test]
+    fn test_ledger_running_balance() {
+        let salary = Account {
+            id: None,
+            name: "Salary".to_string(),
+            family: AccountFamily::Revenue,
+            parent_id: None,
+            path: None,
+        };
+        let rent = Account {
+            id: None,
+            name: "Rent".to_string(),
+            family: AccountFamily::Expense,
+            parent_id: None,
+            path: None,
+        };
+        let line = |entry_id: i32, amount: &str, counter_account: &Account| LedgerLine {
+            entry_id,
+            description: format!("Entry {}", entry_id),
+            event_date: DateTime::parse_from_rfc3339("2024-12-01T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            amount: money(amount),
+            counter_account: counter_account.clone(),
+            running_balance: Money::ZERO,
+        };
+
+        let bank = Account {
+            id: None,
+            name: "Bank".to_string(),
+            family: AccountFamily::Asset,
+            parent_id: None,
+            path: None,
+        };
+        let ledger = Ledger::new(
+            bank,
+            money("500"),
+            vec![line(1, "10000", &salary), line(2, "-1000.50", &rent)],
+        );
+
+        let running: Vec<Money> = ledger.lines.iter().map(|l| l.running_balance).collect();
+        assert_eq!(running, vec![money("10500"), money("9499.50")]);
+        assert_eq!(ledger.opening_balance, money("500"));
+        assert_eq!(ledger.closing_balance, money("9499.50"));
+    }
+
+    #[test]
+    fn test_money_serialization() {
+        assert_eq!(serde_json::json!(money("29.9")), serde_json::json!("29.90"));
+        assert_eq!(serde_json::json!(money("-3")), serde_json::json!("-3.00"));
+        assert_eq!(serde_json::json!(-Money::ZERO), serde_json::json!("0.00"));
+
+        let from_string: Money = serde_json::from_str("\"29.99\"").unwrap();
+        let from_number: Money = serde_json::from_str("29.99").unwrap();
+        let from_integer: Money = serde_json::from_str("30").unwrap();
+        assert_eq!(from_string, Money::from_cents(2999));
+        assert_eq!(from_number, Money::from_cents(2999));
+        assert_eq!(from_integer, Money::from_cents(3000));
+
+        assert!(serde_json::from_str::<Money>("0.125").is_err());
+        assert!(serde_json::from_str::<Money>("\"0.125\"").is_err());
+        assert!(serde_json::from_str::<Money>("\"12,5\"").is_err());
+        assert!(serde_json::from_str::<Money>("\"1000000000000000000\"").is_err());
+        assert_eq!(money("10.500"), Money::from_cents(1050));
+    }
+
+    #[test]
+    fn test_money_is_exact() {
+        let total: Money = std::iter::repeat_n(money("0.10"), 10).sum();
+        assert_eq!(total, money("1"));
+        assert_eq!(money("0.1") + money("0.2"), money("0.3"));
+    }
+
+    #[test]
+    fn test_entry_page_next_offset() {
+        let entry = Entry {
+            id: None,
+            description: "Rent".to_string(),
+            amount: money("1000"),
+            event_date: DateTime::parse_from_rfc3339("2024-12-01T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            credit: Account {
+                id: None,
+                name: "Rent".to_string(),
+                family: AccountFamily::Expense,
+                parent_id: None,
+                path: None,
+            },
+            debit: Account {
+                id: None,
+                name: "Bank".to_string(),
+                family: AccountFamily::Asset,
+                parent_id: None,
+                path: None,
+            },
+            reverses: None,
+        };
+
+        let first = EntryPage::new(vec![entry.clone(), entry.clone()], 3, 2, 0);
+        assert_eq!(first.next_offset, Some(2));
+
+        let last = EntryPage::new(vec![entry], 3, 2, 2);
+        assert_eq!(last.next_offset, None);
+
+        let past_the_end = EntryPage::new(vec![], 3, 2, 10);
+        assert_eq!(past_the_end.next_offset, None);
+    }
+
+    fn day(value: &str) -> chrono::NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn recurrence(
+        frequence: Frequence,
+        start_date: Option<&str>,
+        end_date: Option<&str>,
+    ) -> Recurrence {
+        Recurrence {
+            id: None,
+            description: "Rent".to_string(),
+            amount: money("1000"),
+            credit: Account {
+                id: None,
+                name: "Rent".to_string(),
+                family: AccountFamily::Expense,
+                parent_id: None,
+                path: None,
+            },
+            debit: Account {
+                id: None,
+                name: "Bank".to_string(),
+                family: AccountFamily::Asset,
+                parent_id: None,
+                path: None,
+            },
+            start_date: start_date.map(day),
+            end_date: end_date.map(day),
+            frequence,
+        }
+    }
+
+    #[test]
+    fn test_frequence_periods() {
+        // 2024-12-04 is a Wednesday
+        let date = day("2024-12-04");
+        assert_eq!(Frequence::Daily.period_start(date), date);
+        assert_eq!(Frequence::Weekly.period_start(date), day("2024-12-02"));
+        assert_eq!(Frequence::Monthly.period_start(date), day("2024-12-01"));
+        assert_eq!(Frequence::Yearly.period_start(date), day("2024-01-01"));
+
+        assert_eq!(Frequence::Daily.next_period_start(date), day("2024-12-05"));
+        assert_eq!(Frequence::Weekly.next_period_start(date), day("2024-12-09"));
+        assert_eq!(
+            Frequence::Monthly.next_period_start(date),
+            day("2025-01-01")
+        );
+        assert_eq!(Frequence::Yearly.next_period_start(date), day("2025-01-01"));
+    }
+
+    #[test]
+    fn test_due_occurrences_respect_start_and_inclusive_end() {
+        let monthly = recurrence(Frequence::Monthly, Some("2024-10-15"), Some("2025-01-01"));
+        assert_eq!(
+            monthly.due_occurrences(None, day("2025-03-10")),
+            vec![day("2024-11-01"), day("2024-12-01"), day("2025-01-01")]
+        );
+
+        let weekly = recurrence(Frequence::Weekly, Some("2024-12-02"), None);
+        assert_eq!(
+            weekly.due_occurrences(None, day("2024-12-15")),
+            vec![day("2024-12-02"), day("2024-12-09")]
+        );
+    }
+
+    #[test]
+    fn test_due_occurrences_resume_after_last_posted() {
+        let monthly = recurrence(Frequence::Monthly, Some("2024-01-01"), None);
+        assert_eq!(
+            monthly.due_occurrences(Some(day("2024-11-01")), day("2024-12-31")),
+            vec![day("2024-12-01")]
+        );
+        assert!(
+            monthly
+                .due_occurrences(Some(day("2024-12-01")), day("2024-12-31"))
+                .is_empty()
+        );
+
+        let endless = recurrence(Frequence::Yearly, None, None);
+        assert_eq!(
+            endless.due_occurrences(None, day("2024-06-15")),
+            vec![day("2024-01-01")]
+        );
+
+        let future = recurrence(Frequence::Daily, Some("2025-01-01"), None);
+        assert!(future.due_occurrences(None, day("2024-12-31")).is_empty());
+        assert_eq!(
+            future.occurrence_description(day("2025-01-01")),
+            "Rent 2025-01-01"
+        );
+    }
+
+    #[test]
+    fn test_due_occurrences_are_bounded() {
+        use chrono::Datelike;
+
+        let daily = recurrence(Frequence::Daily, Some("0001-01-01"), None);
+        let occurrences = daily.due_occurrences(None, day("2024-12-31"));
+        assert_eq!(occurrences.len(), MAX_DUE_OCCURRENCES);
+        assert_eq!(occurrences[0], day("0001-01-01"));
+        let next = daily.due_occurrences(occurrences.last().copied(), day("2024-12-31"));
+        assert_eq!(next[0], day("0003-09-28"));
+
+        // No period starts after the last representable date
+        let max = chrono::NaiveDate::MAX;
+        let monthly = recurrence(Frequence::Monthly, None, None);
+        assert!(monthly.due_occurrences(Some(max), max).is_empty());
+        let mut late = recurrence(Frequence::Monthly, None, None);
+        late.start_date = max.with_day(15);
+        assert!(late.due_occurrences(None, max).is_empty());
+        let weekly = recurrence(Frequence::Weekly, None, None);
+        assert_eq!(Frequence::Weekly.checked_period_start(chrono::NaiveDate::MIN), None);
+        assert!(weekly.due_occurrences(None, chrono::NaiveDate::MIN).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_event_serialization_and_account_filter() {
+        let event = LedgerEvent::EntryCreated {
+            id: 7,
+            postings: vec![
+                Movement {
+                    account: 1,
+                    amount: money("12.5"),
+                },
+                Movement {
+                    account: 2,
+                    amount: money("-12.5"),
+                },
+            ],
+        };
+
+        assert_eq!(event.name(), "entry-created");
+        assert_eq!(
+            serde_json::json!(&event),
+            serde_json::json!({
+                "type": "entry-created",
+                "id": 7,
+                "postings": [
+                    { "account": 1, "amount": "12.50" },
+                    { "account": 2, "amount": "-12.50" }
+                ]
+            })
+        );
+
+        assert!(event.concerns(&[]));
+        assert!(event.concerns(&[2, 5]));
+        assert!(!event.concerns(&[5]));
+
+        let balance = LedgerEvent::BalanceChanged {
+            account: 5,
+            balance: money("-3"),
+        };
+        assert_eq!(balance.name(), "balance-changed");
+        assert!(balance.concerns(&[5]));
+        assert!(!balance.concerns(&[1]));
+    }
+
+    #[test]
+    fn test_transaction_postings_balance() {
+        let posting = |name: &str, amount: &str| Posting {
+            account: Account {
+                id: None,
+                name: name.to_string(),
+                family: AccountFamily::Expense,
+                parent_id: None,
+                path: None,
+            },
+            amount: money(amount),
+        };
+        let mut transaction = Transaction {
+            id: None,
+            description: "Supermarket".to_string(),
+            event_date: Utc::now(),
+            postings: vec![
+                posting("Bank", "-42.50"),
+                posting("Groceries", "30"),
+                posting("Household", "12.50"),
+            ],
+            reverses: None,
+        };
+        assert_eq!(transaction.check_balanced(), Ok(()));
+
+        transaction.id = Some(7);
+        let reversal = transaction.reversal(Utc::now());
+        assert_eq!(reversal.id, None);
+        assert_eq!(reversal.reverses, Some(7));
+        assert_eq!(reversal.description, "Reversal of Supermarket");
+        assert_eq!(reversal.check_balanced(), Ok(()));
+        let amounts: Vec<Money> = reversal.postings.iter().map(|p| p.amount).collect();
+        assert_eq!(amounts, vec![money("42.50"), money("-30"), money("-12.50")]);
+
+        transaction.postings[2].amount = money("12");
+        assert_eq!(
+            transaction.check_balanced(),
+            Err("Posting amounts sum to -0.50, not zero".to_string())
+        );
+
+        transaction.postings.truncate(1);
+        assert!(transaction.check_balanced().is_err());
+
+        let entry = Entry {
+            id: Some(3),
+            description: "Rent".to_string(),
+            amount: money("800"),
+            event_date: Utc::now(),
+            credit: posting("Rent", "0").account,
+            debit: posting("Bank", "0").account,
+            reverses: None,
+        };
+        let transaction = Transaction::from(entry);
+        assert_eq!(transaction.id, Some(3));
+        assert_eq!(transaction.check_balanced(), Ok(()));
+        assert_eq!(transaction.postings[0].account.name, "Rent");
+        assert_eq!(transaction.postings[0].amount, money("800"));
+        assert_eq!(transaction.postings[1].account.name, "Bank");
+        assert_eq!(transaction.postings[1].amount, money("-800"));
+    }
+}
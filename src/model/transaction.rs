@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::account::Account;
+use crate::model::entry::{self, Entry};
+use crate::model::money::Money;
+
+use crate::utils::{datefmt_deserialize, datefmt_serialize};
+
+/// One leg of a transaction: positive when the account is credited, negative when debited.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Posting {
+    pub account: Account,
+    pub amount: Money,
+}
+
+/// Entry split across any number of accounts, the amounts of its postings sum to zero.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Transaction {
+    /// Set by the server, ignored on input.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+
+    pub description: String,
+
+    #[serde(
+        serialize_with = "datefmt_serialize",
+        deserialize_with = "datefmt_deserialize"
+    )]
+    pub event_date: DateTime<Utc>,
+
+    pub postings: Vec<Posting>,
+
+    /// Id of the entry this one cancels out, set on reversals only.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub reverses: Option<i32>,
+}
+
+impl Transaction {
+    /// Explains why the postings cannot be recorded.
+    pub fn check_balanced(&self) -> Result<(), String> {
+        if self.postings.len() < 2 {
+            return Err(format!(
+                "A transaction needs at least two postings, found {}",
+                self.postings.len()
+            ));
+        }
+        if self
+            .postings
+            .iter()
+            .any(|posting| posting.amount == Money::ZERO)
+        {
+            return Err("Posting amounts cannot be zero".to_string());
+        }
+
+        let total: Money = self.postings.iter().map(|posting| posting.amount).sum();
+        if total != Money::ZERO {
+            return Err(format!("Posting amounts sum to {}, not zero", total));
+        }
+        Ok(())
+    }
+
+    /// Counter-transaction cancelling this one out, every posting negated.
+    pub fn reversal(&self, event_date: DateTime<Utc>) -> Transaction {
+        Transaction {
+            id: None,
+            description: format!("Reversal of {}", self.description),
+            event_date,
+            postings: self
+                .postings
+                .iter()
+                .map(|posting| Posting {
+                    account: posting.account.clone(),
+                    amount: -posting.amount,
+                })
+                .collect(),
+            reverses: self.id,
+        }
+    }
+}
+
+impl From<Entry> for Transaction {
+    /// Two postings: the amount credited to `credit` and debited from `debit`.
+    fn from(entry: Entry) -> Self {
+        Transaction {
+            id: entry.id,
+            description: entry.description,
+            event_date: entry.event_date,
+            postings: vec![
+                Posting {
+                    account: entry.credit,
+                    amount: entry.amount,
+                },
+                Posting {
+                    account: entry.debit,
+                    amount: -entry.amount,
+                },
+            ],
+            reverses: entry.reverses,
+        }
+    }
+}
+
+/// One page of a transaction listing.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    /// Number of transactions matching the search, all pages included
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Offset of the next page, missing on the last page
+    pub next_offset: Option<i64>,
+}
+
+impl TransactionPage {
+    pub fn new(transactions: Vec<Transaction>, total: i64, limit: i64, offset: i64) -> Self {
+        TransactionPage {
+            next_offset: entry::next_offset(transactions.len(), total, offset),
+            transactions,
+            total,
+            limit,
+            offset,
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
 use deadpool_postgres::Pool;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, broadcast};
 use tokio_postgres::{
     Config, Socket,
     tls::{MakeTlsConnect, TlsConnect},
@@ -13,121 +14,1236 @@ mod dao;
 mod db_listener;
 mod dto;
 
+pub mod error;
 pub mod filter;
+pub mod validation;
 
 use crate::{
-    model,
+    model::{self, money::Money, problem::FieldError},
     repository::{
         self,
         cache::Repository as CacheRepository,
         db_listener::{DatabaseListener, NotificationHandler},
         dto::DtoModelNoRef,
+        error::RepositoryError,
     },
 };
 
 pub struct Repository {
     dao: dao::Dao,
     account_repository: cache::AccountRepository,
+    rules: validation::ValidationRules,
 }
 
 impl Repository {
     #[instrument(name = "Repository initialization", skip(pool))]
-    pub async fn new(pool: Pool) -> Repository {
+    pub async fn new(pool: Pool, rules: validation::ValidationRules) -> Repository {
         let dao = dao::new(pool);
         let account_repository = initialize_account_repository(&dao).await;
 
         repository::Repository {
             dao,
             account_repository,
+            rules,
         }
     }
 
+    /// Returns the created account, with its id.
     pub async fn insert_account(
         &self,
         account: &model::account::Account,
-    ) -> Result<i32, Box<dyn std::error::Error>> {
-        let account_dto: dto::Account = dto::DtoModelNoRef::from_model(account);
-        let res = self.dao.insert_account(&account_dto).await?;
-        self.account_repository.add(res, account.clone()).await?;
-        Ok(res)
+    ) -> Result<model::account::Account, RepositoryError> {
+        let mut account_dto: dto::Account = dto::DtoModelNoRef::from_model(account);
+        account_dto.id = self.dao.insert_account(&account_dto).await?;
+        self.cache_account(&account_dto).await
     }
 
     pub async fn get_account(
         &self,
         id: i32,
-    ) -> Result<model::account::Account, Box<dyn std::error::Error>> {
+    ) -> Result<model::account::Account, RepositoryError> {
+        let account = self.find_account(id).await?;
+        account.ok_or_else(|| RepositoryError::NotFound(format!("Account {} not found", id)))
+    }
+
+    /// Returns `None` when no account has this id.
+    pub async fn find_account(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
         if let Some(account) = self.account_repository.get(&id).await? {
-            return Ok(account);
+            return Ok(Some(account));
         }
 
         let account_dto = self.dao.get_account(id).await?;
-        let account = dto::DtoModelNoRef::to_model(&account_dto);
-        self.account_repository.add(id, account.clone()).await?;
-        Ok(account)
+        let Some(account_dto) = account_dto else {
+            return Ok(None);
+        };
+        Ok(Some(self.cache_account(&account_dto).await?))
     }
 
-    pub async fn insert_entry(
+    pub async fn get_accounts(
         &self,
-        entry: &model::entry::Entry,
-    ) -> Result<i32, Box<dyn std::error::Error>> {
-        let mut entry_dto: dto::Entry = dto::DtoModelNoRef::from_model(entry);
+    ) -> Result<Vec<model::account::Account>, RepositoryError> {
+        let accounts_dto = self.dao.get_accounts().await?;
+        self.account_repository
+            .add_all(accounts_dto.iter().map(|account_dto| account_dto.to_model()))
+            .await;
 
-        entry_dto.credit_id = self
-            .account_repository
-            .get_id_by_name(entry.credit.name.as_str())
-            .await?;
-        entry_dto.debit_id = self
-            .account_repository
-            .get_id_by_name(entry.debit.name.as_str())
+        // Read back once all are cached, parents listed after their children have a path too
+        let mut accounts = Vec::new();
+        for account_dto in accounts_dto {
+            if let Some(account) = self.account_repository.get(&account_dto.id).await? {
+                accounts.push(account);
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    /// Caches the account, returns it with its path.
+    async fn cache_account(
+        &self,
+        account_dto: &dto::Account,
+    ) -> Result<model::account::Account, RepositoryError> {
+        self.account_repository
+            .add(account_dto.id, account_dto.to_model())
             .await?;
+        let account = self.account_repository.get(&account_dto.id).await?;
+        Ok(account.unwrap_or_else(|| account_dto.to_model()))
+    }
+
+    /// Replaces the account, returns `None` when no account has this id.
+    pub async fn update_account(
+        &self,
+        id: i32,
+        account: &model::account::Account,
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
+        let family = dto::family_to_int(&account.family);
+        let parent = Some(account.parent_id);
+        self.apply_account_update(id, Some(&account.name), Some(family), parent)
+            .await
+    }
+
+    /// Updates the given fields of the account, returns `None` when no account has this id.
+    pub async fn patch_account(
+        &self,
+        id: i32,
+        patch: &model::account::AccountPatch,
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
+        let family = patch.family.as_ref().map(dto::family_to_int);
+        let parent = patch.parent_id.map(Some);
+        self.apply_account_update(id, patch.name.as_deref(), family, parent)
+            .await
+    }
+
+    async fn apply_account_update(
+        &self,
+        id: i32,
+        name: Option<&str>,
+        family: Option<i32>,
+        parent: Option<Option<i32>>,
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
+        let updated = self.dao.update_account(id, name, family, parent).await?;
+        match updated {
+            Some(account_dto) => Ok(Some(self.cache_account(&account_dto).await?)),
+            None => {
+                self.account_repository.remove(&id).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns `false` when no account has this id.
+    pub async fn delete_account(&self, id: i32) -> Result<bool, RepositoryError> {
+        let deleted = self.dao.delete_account(id).await?;
+        self.account_repository.remove(&id).await?;
+        Ok(deleted > 0)
+    }
+
+    /// Returns `None` when no account has this id.
+    pub async fn get_account_balance(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::balance::AccountBalance>, RepositoryError> {
+        let balance_dto = self.dao.get_account_balance(id).await?;
+        match balance_dto {
+            Some(balance_dto) => Ok(Some(model::balance::AccountBalance {
+                account: self.get_account(balance_dto.id).await?,
+                balance: balance_dto.balance,
+                subtotal: self.dao.get_account_subtotal(id).await?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_balances(
+        &self,
+    ) -> Result<Vec<model::balance::FamilyBalances>, RepositoryError> {
+        let mut balances = self.get_account_balances().await?;
+        model::balance::AccountBalance::roll_up(&mut balances);
+        Ok(model::balance::FamilyBalances::group(balances))
+    }
+
+    /// Top-level accounts with their sub-accounts nested, subtotals rolled up.
+    pub async fn get_account_tree(
+        &self,
+    ) -> Result<Vec<model::balance::AccountTree>, RepositoryError> {
+        let balances = self.get_account_balances().await?;
+        Ok(model::balance::AccountTree::build(balances))
+    }
+
+    /// Assets against liabilities and equity over the entries up to `as_of`, inclusive.
+    pub async fn get_balance_sheet(
+        &self,
+        as_of: DateTime<Utc>,
+    ) -> Result<model::report::BalanceSheet, RepositoryError> {
+        let mut filters = filter::Filters::new();
+        filters.and(
+            &filter::EntryFields::EventDate,
+            filter::Operator::LessThanOrEqual,
+            as_of,
+        );
+        let balances_dto = self.dao.get_filtered_account_balances(&filters).await?;
+        let mut balances = self.balances_from_dto(balances_dto).await?;
+
+        model::balance::AccountBalance::roll_up(&mut balances);
+        Ok(model::report::BalanceSheet::new(as_of, &balances))
+    }
+
+    /// Debits and credits of every account over the entries up to `as_of`, inclusive,
+    /// along with the accounts whose materialized balance is stale.
+    pub async fn get_trial_balance(
+        &self,
+        as_of: DateTime<Utc>,
+    ) -> Result<model::report::TrialBalance, RepositoryError> {
+        let mut filters = filter::Filters::new();
+        filters.and(
+            &filter::EntryFields::EventDate,
+            filter::Operator::LessThanOrEqual,
+            as_of,
+        );
+
+        let mut lines = Vec::new();
+        for line_dto in self.dao.get_trial_balance_lines(&filters).await? {
+            lines.push(model::report::TrialBalanceLine {
+                account: self.get_account(line_dto.account_id).await?,
+                debits: line_dto.debits,
+                credits: line_dto.credits,
+                balance: line_dto.credits - line_dto.debits,
+            });
+        }
 
-        let res = self.dao.insert_entry(&entry_dto).await?;
+        let mut mismatches = Vec::new();
+        for mismatch_dto in self.dao.get_balance_mismatches().await? {
+            mismatches.push(model::report::BalanceMismatch {
+                account: self.get_account(mismatch_dto.account_id).await?,
+                recorded: mismatch_dto.recorded,
+                computed: mismatch_dto.computed,
+            });
+        }
+
+        Ok(model::report::TrialBalance::new(as_of, lines, mismatches))
+    }
+
+    /// Revenues and expenses over each period, see `model::report::ReportPeriod::split`.
+    pub async fn get_income_statement(
+        &self,
+        periods: Vec<model::report::ReportPeriod>,
+    ) -> Result<model::report::IncomeStatement, RepositoryError> {
+        let mut balances = Vec::new();
+        for period in &periods {
+            let balances_dto = self
+                .dao
+                .get_filtered_account_balances(&period_filters(period))
+                .await?;
+            let mut period_balances = self.balances_from_dto(balances_dto).await?;
+            model::balance::AccountBalance::roll_up(&mut period_balances);
+            balances.push(period_balances);
+        }
+
+        Ok(model::report::IncomeStatement::new(periods, &balances))
+    }
+
+    /// Cash flow of the given Asset accounts and their sub-accounts, of every Asset
+    /// account when none is given.
+    pub async fn get_cash_flow(
+        &self,
+        account_ids: &[i32],
+        periods: Vec<model::report::ReportPeriod>,
+    ) -> Result<model::report::CashFlowStatement, RepositoryError> {
+        let accounts = self.get_accounts().await?;
+        for id in account_ids {
+            match accounts.iter().find(|account| account.id == Some(*id)) {
+                None => {
+                    return Err(RepositoryError::Validation(format!("Unknown account {}", id)));
+                }
+                Some(account) if account.family != model::account::AccountFamily::Asset => {
+                    return Err(RepositoryError::Validation(format!(
+                        "Account '{}' is not an Asset account",
+                        account.reference()
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Sub-accounts share the family of their parent, following them is enough
+        let mut cash_ids: Vec<i32> = if account_ids.is_empty() {
+            accounts
+                .iter()
+                .filter(|account| account.family == model::account::AccountFamily::Asset)
+                .filter_map(|account| account.id)
+                .collect()
+        } else {
+            account_ids.to_vec()
+        };
+        let mut i = 0;
+        while i < cash_ids.len() {
+            let parent = cash_ids[i];
+            for account in &accounts {
+                if let Some(id) = account.id
+                    && account.parent_id == Some(parent)
+                    && !cash_ids.contains(&id)
+                {
+                    cash_ids.push(id);
+                }
+            }
+            i += 1;
+        }
+        cash_ids.sort_unstable();
+
+        let mut flows = Vec::new();
+        for period in &periods {
+            let mut period_flows = Vec::new();
+            for flow_dto in self.dao.get_cash_flows(&period_filters(period), &cash_ids).await? {
+                period_flows.push(model::report::CashFlow {
+                    account: self.get_account(flow_dto.account_id).await?,
+                    inflow: flow_dto.inflow,
+                    outflow: flow_dto.outflow,
+                });
+            }
+            flows.push(period_flows);
+        }
+
+        let cash_accounts = accounts
+            .into_iter()
+            .filter(|account| account.id.is_some_and(|id| cash_ids.contains(&id)))
+            .collect();
+        Ok(model::report::CashFlowStatement::new(cash_accounts, periods, &flows))
+    }
+
+    pub async fn get_net_worth(
+        &self,
+        periods: Vec<model::report::ReportPeriod>,
+    ) -> Result<model::report::NetWorthSeries, RepositoryError> {
+        let ends = periods
+            .iter()
+            .map(|period| day_after(period.end_date))
+            .collect::<Result<Vec<DateTime<Utc>>, RepositoryError>>()?;
+        let movements: Vec<model::report::FamilyMovement> = self
+            .dao
+            .get_family_movements(&ends)
+            .await?
+            .iter()
+            .map(dto::FamilyMovement::to_model)
+            .collect();
+
+        Ok(model::report::NetWorthSeries::new(periods, &movements))
+    }
+
+    /// Own balance of every account, subtotals are left to roll up.
+    async fn get_account_balances(
+        &self,
+    ) -> Result<Vec<model::balance::AccountBalance>, RepositoryError> {
+        let balances_dto = self.dao.get_account_balances().await?;
+        self.balances_from_dto(balances_dto).await
+    }
 
-        Ok(res)
+    /// Builds the balance models, fetching their accounts.
+    async fn balances_from_dto(
+        &self,
+        balances_dto: Vec<dto::AccountBalance>,
+    ) -> Result<Vec<model::balance::AccountBalance>, RepositoryError> {
+        let mut balances = Vec::new();
+        for balance_dto in balances_dto {
+            balances.push(model::balance::AccountBalance {
+                account: self.get_account(balance_dto.id).await?,
+                balance: balance_dto.balance,
+                subtotal: balance_dto.balance,
+            });
+        }
+        Ok(balances)
+    }
+
+    /// Lists the entries touching the account between the two dates, both inclusive.
+    /// Returns `None` when no account has this id.
+    pub async fn get_account_ledger(
+        &self,
+        id: i32,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+    ) -> Result<Option<model::ledger::Ledger>, RepositoryError> {
+        let Some(account) = self.find_account(id).await? else {
+            return Ok(None);
+        };
+
+        let opening_balance = match start_date {
+            Some(start_date) => self.dao.get_ledger_balance_before(id, start_date).await?,
+            None => model::money::Money::ZERO,
+        };
+
+        let lines_dto = self.dao.get_ledger_lines(id, start_date, end_date).await?;
+        let mut lines = Vec::new();
+        for line_dto in lines_dto {
+            lines.push(model::ledger::LedgerLine {
+                entry_id: line_dto.entry_id,
+                description: line_dto.description,
+                event_date: line_dto.event_date,
+                amount: line_dto.amount,
+                counter_account: self.get_account(line_dto.counter_account_id).await?,
+                running_balance: model::money::Money::ZERO,
+            });
+        }
+
+        Ok(Some(model::ledger::Ledger::new(
+            account,
+            opening_balance,
+            lines,
+        )))
+    }
+
+    /// Records the entry as a transaction of two postings, returns the created entry.
+    pub async fn insert_entry(
+        &self,
+        entry: &model::entry::Entry,
+    ) -> Result<model::entry::Entry, RepositoryError> {
+        let transaction_dto = self.validate_entry(entry, None).await?;
+        let id = self.dao.insert_transaction(&transaction_dto).await?;
+        self.get_entry(id).await
     }
 
     pub async fn get_entry(
         &self,
         id: i32,
-    ) -> Result<model::entry::Entry, Box<dyn std::error::Error>> {
+    ) -> Result<model::entry::Entry, RepositoryError> {
         let entry_dto = self.dao.get_entry(id).await?;
-        let credit_account = self.get_account(entry_dto.credit_id).await?;
-        let debit_account = self.get_account(entry_dto.debit_id).await?;
-
-        let mut res = dto::DtoModelNoRef::to_model(&entry_dto);
-        res.credit = credit_account;
-        res.debit = debit_account;
-        Ok(res)
+        let entry_dto =
+            entry_dto.ok_or_else(|| RepositoryError::NotFound(format!("Entry {} not found", id)))?;
+        self.entry_from_dto(&entry_dto).await
     }
 
     pub async fn get_entries(
         &self,
         filter: &filter::Filters<filter::EntryFields>,
-    ) -> Result<Vec<model::entry::Entry>, Box<dyn std::error::Error>> {
-        let entries_dto = self.dao.get_entries(filter).await?;
+        sort: &filter::Sort<filter::EntryFields>,
+        pagination: &filter::Pagination,
+    ) -> Result<model::entry::EntryPage, RepositoryError> {
+        let (entries_dto, total) = self.dao.get_entries(filter, sort, pagination).await?;
 
         let mut entries = Vec::new();
         for entry_dto in entries_dto {
-            let credit_account = self.get_account(entry_dto.credit_id).await?;
-            let debit_account = self.get_account(entry_dto.debit_id).await?;
-            let mut entry = dto::DtoModelNoRef::to_model(&entry_dto);
-            entry.credit = credit_account;
-            entry.debit = debit_account;
-            entries.push(entry);
+            entries.push(self.entry_from_dto(&entry_dto).await?);
+        }
+
+        Ok(model::entry::EntryPage::new(
+            entries,
+            total,
+            pagination.limit,
+            pagination.offset,
+        ))
+    }
+
+    /// Replaces the entry by a transaction of two postings. Split entries are rejected,
+    /// they are only replaced through `update_transaction` so no posting is dropped.
+    /// Returns `None` when no entry has this id.
+    pub async fn update_entry(
+        &self,
+        id: i32,
+        entry: &model::entry::Entry,
+    ) -> Result<Option<model::entry::Entry>, RepositoryError> {
+        let Some(stored) = self.dao.get_transaction(id).await? else {
+            return Ok(None);
+        };
+        if stored.postings.len() > 2 {
+            return Err(RepositoryError::Conflict(format!(
+                "Entry {} is split across {} postings, replace it with PUT /transaction/{}",
+                id,
+                stored.postings.len(),
+                id
+            )));
+        }
+        let transaction_dto = self
+            .validate_entry(entry, Some(stored.event_date))
+            .await?;
+        if !self.dao.update_transaction(id, &transaction_dto).await? {
+            return Ok(None);
+        }
+
+        Ok(Some(self.get_entry(id).await?))
+    }
+
+    /// Returns the created transaction, with its id.
+    pub async fn insert_transaction(
+        &self,
+        transaction: &model::transaction::Transaction,
+    ) -> Result<model::transaction::Transaction, RepositoryError> {
+        let mut transaction_dto = self
+            .validate_transaction(transaction, None, posting_field, vec![])
+            .await?;
+        transaction_dto.id = self.dao.insert_transaction(&transaction_dto).await?;
+        self.transaction_from_dto(&transaction_dto).await
+    }
+
+    pub async fn get_transaction(
+        &self,
+        id: i32,
+    ) -> Result<model::transaction::Transaction, RepositoryError> {
+        let transaction = self.find_transaction(id).await?;
+        transaction
+            .ok_or_else(|| RepositoryError::NotFound(format!("Transaction {} not found", id)))
+    }
+
+    /// Any entry seen as a transaction, returns `None` when no entry has this id.
+    pub async fn find_transaction(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::transaction::Transaction>, RepositoryError> {
+        let transaction_dto = self.dao.get_transaction(id).await?;
+        match transaction_dto {
+            Some(transaction_dto) => Ok(Some(self.transaction_from_dto(&transaction_dto).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_transactions(
+        &self,
+        filter: &filter::Filters<filter::EntryFields>,
+        sort: &filter::Sort<filter::EntryFields>,
+        pagination: &filter::Pagination,
+    ) -> Result<model::transaction::TransactionPage, RepositoryError> {
+        let (transactions_dto, total) = self.dao.get_transactions(filter, sort, pagination).await?;
+
+        let mut transactions = Vec::new();
+        for transaction_dto in transactions_dto {
+            transactions.push(self.transaction_from_dto(&transaction_dto).await?);
+        }
+
+        Ok(model::transaction::TransactionPage::new(
+            transactions,
+            total,
+            pagination.limit,
+            pagination.offset,
+        ))
+    }
+
+    /// Replaces the transaction and all of its postings.
+    /// Returns `None` when no entry has this id.
+    pub async fn update_transaction(
+        &self,
+        id: i32,
+        transaction: &model::transaction::Transaction,
+    ) -> Result<Option<model::transaction::Transaction>, RepositoryError> {
+        let Some(stored) = self.dao.get_transaction(id).await? else {
+            return Ok(None);
+        };
+        let transaction_dto = self
+            .validate_transaction(transaction, Some(stored.event_date), posting_field, vec![])
+            .await?;
+        if !self.dao.update_transaction(id, &transaction_dto).await? {
+            return Ok(None);
+        }
+
+        self.find_transaction(id).await
+    }
+
+    /// Returns `false` when no entry has this id.
+    pub async fn delete_entry(&self, id: i32) -> Result<bool, RepositoryError> {
+        let deleted = self.dao.delete_entry(id).await?;
+        Ok(deleted > 0)
+    }
+
+    /// Posts a counter-transaction with every posting negated, linked to the original one.
+    /// Split entries are reversed too. Returns `None` when no entry has this id.
+    pub async fn reverse_entry(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::transaction::Transaction>, RepositoryError> {
+        let Some(original) = self.find_transaction(id).await? else {
+            return Ok(None);
+        };
+
+        let reversal = self.insert_transaction(&original.reversal(Utc::now())).await?;
+        tracing::event!(Level::DEBUG, "Entry {} reversed by entry {:?}", id, reversal.id);
+
+        Ok(Some(reversal))
+    }
+
+    /// Returns the created recurrence, with its id.
+    pub async fn insert_recurrence(
+        &self,
+        recurrence: &model::recurrence::Recurrence,
+    ) -> Result<model::recurrence::Recurrence, RepositoryError> {
+        let mut recurrence_dto = self.recurrence_to_dto(recurrence).await?;
+        recurrence_dto.id = self.dao.insert_recurrence(&recurrence_dto).await?;
+        self.recurrence_from_dto(&recurrence_dto).await
+    }
+
+    /// Returns `None` when no recurrence has this id.
+    pub async fn get_recurrence(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::recurrence::Recurrence>, RepositoryError> {
+        let recurrence_dto = self.dao.get_recurrence(id).await?;
+        match recurrence_dto {
+            Some(recurrence_dto) => Ok(Some(self.recurrence_from_dto(&recurrence_dto).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_recurrences(
+        &self,
+    ) -> Result<Vec<model::recurrence::Recurrence>, RepositoryError> {
+        let recurrences_dto = self.dao.get_recurrences().await?;
+
+        let mut recurrences = Vec::new();
+        for recurrence_dto in recurrences_dto {
+            recurrences.push(self.recurrence_from_dto(&recurrence_dto).await?);
+        }
+
+        Ok(recurrences)
+    }
+
+    /// Replaces the recurrence, returns `None` when no recurrence has this id.
+    /// Entries already posted are left untouched.
+    pub async fn update_recurrence(
+        &self,
+        id: i32,
+        recurrence: &model::recurrence::Recurrence,
+    ) -> Result<Option<model::recurrence::Recurrence>, RepositoryError> {
+        let recurrence_dto = self.recurrence_to_dto(recurrence).await?;
+        let updated = self.dao.update_recurrence(id, &recurrence_dto).await?;
+        match updated {
+            Some(recurrence_dto) => Ok(Some(self.recurrence_from_dto(&recurrence_dto).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `false` when no recurrence has this id.
+    pub async fn delete_recurrence(&self, id: i32) -> Result<bool, RepositoryError> {
+        let deleted = self.dao.delete_recurrence(id).await?;
+        Ok(deleted > 0)
+    }
+
+    /// Returns the created budget, with its id.
+    pub async fn insert_budget(
+        &self,
+        budget: &model::budget::Budget,
+    ) -> Result<model::budget::Budget, RepositoryError> {
+        let mut budget_dto = self.budget_to_dto(budget).await?;
+        budget_dto.id = self.dao.insert_budget(&budget_dto).await?;
+        self.budget_from_dto(&budget_dto).await
+    }
+
+    /// Returns `None` when no budget has this id.
+    pub async fn get_budget(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::budget::Budget>, RepositoryError> {
+        match self.dao.get_budget(id).await? {
+            Some(budget_dto) => Ok(Some(self.budget_from_dto(&budget_dto).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_budgets(&self) -> Result<Vec<model::budget::Budget>, RepositoryError> {
+        let budgets_dto = self.dao.get_budgets().await?;
+
+        let mut budgets = Vec::new();
+        for budget_dto in budgets_dto {
+            budgets.push(self.budget_from_dto(&budget_dto).await?);
         }
 
-        Ok(entries)
+        Ok(budgets)
+    }
+
+    /// Replaces the budget, returns `None` when no budget has this id.
+    pub async fn update_budget(
+        &self,
+        id: i32,
+        budget: &model::budget::Budget,
+    ) -> Result<Option<model::budget::Budget>, RepositoryError> {
+        let budget_dto = self.budget_to_dto(budget).await?;
+        match self.dao.update_budget(id, &budget_dto).await? {
+            Some(budget_dto) => Ok(Some(self.budget_from_dto(&budget_dto).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `false` when no budget has this id.
+    pub async fn delete_budget(&self, id: i32) -> Result<bool, RepositoryError> {
+        let deleted = self.dao.delete_budget(id).await?;
+        Ok(deleted > 0)
+    }
+
+    /// Budget against the entries of its period containing `date`.
+    /// Returns `None` when no budget has this id.
+    pub async fn get_budget_status(
+        &self,
+        id: i32,
+        date: NaiveDate,
+    ) -> Result<Option<model::budget::BudgetStatus>, RepositoryError> {
+        let Some(budget) = self.get_budget(id).await? else {
+            return Ok(None);
+        };
+        let period = budget.period(date).ok_or_else(|| out_of_range(date))?;
+        let account_id = budget.account.id.unwrap_or_default();
+        let balance = self
+            .dao
+            .get_filtered_account_subtotal(account_id, &period_filters(&period))
+            .await?;
+
+        Ok(Some(model::budget::BudgetStatus::new(budget, period, balance)))
+    }
+
+    /// Returns the created transfer, with its id and month normalized.
+    pub async fn insert_envelope_transfer(
+        &self,
+        transfer: &model::envelope::EnvelopeTransfer,
+    ) -> Result<model::envelope::EnvelopeTransfer, RepositoryError> {
+        let mut transfer_dto = self.envelope_transfer_to_dto(transfer).await?;
+        transfer_dto.id = self.dao.insert_envelope_transfer(&transfer_dto).await?;
+        self.envelope_transfer_from_dto(&transfer_dto).await
+    }
+
+    /// Returns `None` when no transfer has this id.
+    pub async fn get_envelope_transfer(
+        &self,
+        id: i32,
+    ) -> Result<Option<model::envelope::EnvelopeTransfer>, RepositoryError> {
+        match self.dao.get_envelope_transfer(id).await? {
+            Some(transfer_dto) => Ok(Some(self.envelope_transfer_from_dto(&transfer_dto).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Transfers of the month containing `date`.
+    pub async fn get_envelope_transfers(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<model::envelope::EnvelopeTransfer>, RepositoryError> {
+        let month = model::recurrence::Frequence::Monthly.period_start(date);
+        let transfers_dto = self.dao.get_month_envelope_transfers(month).await?;
+
+        let mut transfers = Vec::new();
+        for transfer_dto in &transfers_dto {
+            transfers.push(self.envelope_transfer_from_dto(transfer_dto).await?);
+        }
+
+        Ok(transfers)
+    }
+
+    /// Returns `false` when no transfer has this id.
+    pub async fn delete_envelope_transfer(&self, id: i32) -> Result<bool, RepositoryError> {
+        let deleted = self.dao.delete_envelope_transfer(id).await?;
+        Ok(deleted > 0)
+    }
+
+    /// Envelopes over the month containing `date`. An envelope counts the spending
+    /// from the month of its first transfer on.
+    pub async fn get_envelope_month(
+        &self,
+        date: NaiveDate,
+    ) -> Result<model::envelope::EnvelopeMonth, RepositoryError> {
+        let period =
+            model::report::ReportPeriod::containing(date, model::recurrence::Frequence::Monthly)
+                .ok_or_else(|| out_of_range(date))?;
+
+        // First month, transferred before the month, assigned during it, by account
+        let mut transferred: BTreeMap<i32, (NaiveDate, Money, Money)> = BTreeMap::new();
+        for transfer in self.dao.get_envelope_transfers(period.start_date).await? {
+            let sides = [(transfer.source, -transfer.amount), (transfer.target, transfer.amount)];
+            for (account_id, amount) in sides {
+                let Some(account_id) = account_id else {
+                    continue;
+                };
+                let totals = transferred
+                    .entry(account_id)
+                    .or_insert((transfer.month, Money::ZERO, Money::ZERO));
+                match transfer.month < period.start_date {
+                    true => totals.1 += amount,
+                    false => totals.2 += amount,
+                }
+            }
+        }
+
+        let budgets = self.dao.get_budgets().await?;
+        let mut envelopes = Vec::new();
+        for (account_id, (first_month, transferred_before, assigned)) in transferred {
+            let spent_before = match first_month < period.start_date {
+                true => {
+                    let filters = days_filters(first_month, period.start_date - Days::new(1));
+                    self.dao.get_filtered_account_subtotal(account_id, &filters).await?
+                }
+                false => Money::ZERO,
+            };
+            let spent = self
+                .dao
+                .get_filtered_account_subtotal(account_id, &period_filters(&period))
+                .await?;
+            let planned = budgets
+                .iter()
+                .filter(|budget| budget.account_id == account_id)
+                .map(dto::DtoModelNoRef::to_model)
+                .find(|budget: &model::budget::Budget| {
+                    budget.frequence == model::recurrence::Frequence::Monthly
+                });
+            envelopes.push(model::envelope::Envelope::new(
+                self.get_account(account_id).await?,
+                planned.map(|budget| budget.amount),
+                transferred_before - spent_before,
+                assigned,
+                spent,
+            ));
+        }
+
+        let end = day_after(period.end_date)?;
+        let assets = self
+            .dao
+            .get_family_movements(&[end])
+            .await?
+            .iter()
+            .map(dto::FamilyMovement::to_model)
+            .filter(|movement| movement.family == model::account::AccountFamily::Asset)
+            .map(|movement| movement.amount)
+            .sum();
+
+        Ok(model::envelope::EnvelopeMonth::new(period, assets, envelopes))
+    }
+
+    /// Moves what is left in the envelopes at the end of the month containing `date`
+    /// back to the pool, instead of rolling it over. Overspent envelopes are left as is.
+    pub async fn sweep_envelopes(
+        &self,
+        date: NaiveDate,
+    ) -> Result<model::envelope::EnvelopeMonth, RepositoryError> {
+        let envelope_month = self.get_envelope_month(date).await?;
+        let transfers_dto: Vec<dto::EnvelopeTransfer> = envelope_month
+            .envelopes
+            .iter()
+            .filter(|envelope| envelope.available > Money::ZERO)
+            .map(|envelope| dto::EnvelopeTransfer {
+                id: -1,
+                month: envelope_month.period.start_date,
+                source: envelope.account.id,
+                target: None,
+                amount: envelope.available,
+            })
+            .collect();
+        // A month is swept whole or not at all, a failed sweep can simply run again
+        self.dao.insert_envelope_transfers(&transfers_dto).await?;
+
+        self.get_envelope_month(date).await
+    }
+
+    /// Posts an entry for every occurrence due by `today` that is not posted yet.
+    /// Safe to run any number of times, returns the number of created entries.
+    /// A failing recurrence is logged and skipped, the others are still posted.
+    pub async fn materialize_recurrences(
+        &self,
+        today: NaiveDate,
+    ) -> Result<u64, RepositoryError> {
+        let recurrences_dto = self.dao.get_recurrences().await?;
+
+        let mut created = 0;
+        for recurrence_dto in recurrences_dto {
+            match self.materialize_occurrences(&recurrence_dto, today).await {
+                Ok(posted) => created += posted,
+                Err(e) => tracing::event!(
+                    Level::ERROR,
+                    "Failed to post recurrence {}: {}",
+                    recurrence_dto.id,
+                    e
+                ),
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Same as `materialize_recurrences`, for a single recurrence.
+    /// Returns 0 when no recurrence has this id.
+    pub async fn materialize_recurrence(
+        &self,
+        id: i32,
+        today: NaiveDate,
+    ) -> Result<u64, RepositoryError> {
+        let recurrence_dto = self.dao.get_recurrence(id).await?;
+        match recurrence_dto {
+            Some(recurrence_dto) => self.materialize_occurrences(&recurrence_dto, today).await,
+            None => Ok(0),
+        }
+    }
+
+    async fn materialize_occurrences(
+        &self,
+        recurrence_dto: &dto::Recurrence,
+        today: NaiveDate,
+    ) -> Result<u64, RepositoryError> {
+        let recurrence = self.recurrence_from_dto(recurrence_dto).await?;
+        let last = self.dao.get_last_occurrence(recurrence_dto.id).await?;
+
+        let mut created = 0;
+        for occurrence in recurrence.due_occurrences(last, today) {
+            let entry = model::entry::Entry {
+                id: None,
+                description: recurrence.occurrence_description(occurrence),
+                amount: recurrence.amount,
+                event_date: occurrence.and_time(NaiveTime::MIN).and_utc(),
+                credit: recurrence.credit.clone(),
+                debit: recurrence.debit.clone(),
+                reverses: None,
+            };
+            let transaction_dto = self.validate_entry(&entry, None).await?;
+            created += self
+                .dao
+                .insert_occurrence(recurrence_dto.id, occurrence, &transaction_dto)
+                .await?;
+        }
+
+        Ok(created)
+    }
+
+    /// Applies a change notified on the accounts channel to the account cache.
+    async fn apply_account_change(
+        &self,
+        change: dto::AccountChange,
+    ) -> Result<(), RepositoryError> {
+        if change.operation == dto::Operation::Delete {
+            self.account_repository.remove(&change.id).await?;
+            return Ok(());
+        }
+
+        let account_dto = dto::Account {
+            id: change.id,
+            name: change.name,
+            family: change.family,
+            parent: change.parent,
+        };
+        self.account_repository
+            .add(account_dto.id, account_dto.to_model())
+            .await?;
+        Ok(())
+    }
+
+    /// Reloads the whole account cache, dropping accounts deleted in the meantime.
+    async fn reload_accounts(&self) -> Result<(), RepositoryError> {
+        let accounts_dto = self.dao.get_accounts().await?;
+
+        self.account_repository.clear().await;
+        self.account_repository
+            .add_all(accounts_dto.iter().map(|account_dto| account_dto.to_model()))
+            .await;
+        Ok(())
+    }
+
+    /// Id of an account referred to by path in the input, unknown paths are rejected.
+    async fn account_id_by_path(&self, path: &str) -> Result<i32, RepositoryError> {
+        self.account_repository
+            .get_id_by_path(path)
+            .await
+            .ok_or_else(|| RepositoryError::Validation(format!("Unknown account '{}'", path)))
+    }
+
+    /// Account referred to by path in the `field` of the input, an unknown path adds a field error.
+    async fn resolve_account(
+        &self,
+        field: &str,
+        path: &str,
+        errors: &mut Vec<FieldError>,
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
+        match self.account_repository.get_id_by_path(path).await {
+            Some(id) => self.find_account(id).await,
+            None => {
+                errors.push(FieldError::new(field, format!("unknown account '{}'", path)));
+                Ok(None)
+            }
+        }
+    }
+
+    /// The accepted window applies to the event dates clients set, an edit keeping
+    /// the stored date of an older entry is accepted so it can still be corrected.
+    fn check_event_date(
+        &self,
+        event_date: DateTime<Utc>,
+        stored_date: Option<DateTime<Utc>>,
+    ) -> Option<FieldError> {
+        if stored_date == Some(event_date) {
+            return None;
+        }
+        self.rules.check_event_date("event_date", event_date, Utc::now())
+    }
+
+    /// Checks the entry against the validation rules as a transaction of two postings,
+    /// see `validate_transaction`. Errors name the credit and debit fields.
+    async fn validate_entry(
+        &self,
+        entry: &model::entry::Entry,
+        stored_date: Option<DateTime<Utc>>,
+    ) -> Result<dto::Transaction, RepositoryError> {
+        let errors = self.rules.check_amount("amount", entry.amount).into_iter().collect();
+        let transaction = model::transaction::Transaction::from(entry.clone());
+        match self
+            .validate_transaction(&transaction, stored_date, entry_field, errors)
+            .await
+        {
+            // Both postings of an entry balance, a zero amount is already reported
+            Err(RepositoryError::InvalidFields(mut errors)) => {
+                errors.retain(|error| error.field != "postings");
+                Err(RepositoryError::InvalidFields(errors))
+            }
+            result => result,
+        }
+    }
+
+    /// Builds the recurrence DTO once checked against the validation rules, resolving
+    /// account paths to their ids.
+    async fn recurrence_to_dto(
+        &self,
+        recurrence: &model::recurrence::Recurrence,
+    ) -> Result<dto::Recurrence, RepositoryError> {
+        let mut errors = vec![];
+        let credit = self
+            .resolve_account("credit", recurrence.credit.reference(), &mut errors)
+            .await?;
+        let debit = self
+            .resolve_account("debit", recurrence.debit.reference(), &mut errors)
+            .await?;
+
+        errors.extend(self.rules.check_amount("amount", recurrence.amount));
+        // Occurrences are posted from the start date on, it falls in the event date window
+        if let Some(start_date) = recurrence.start_date {
+            let start = start_date.and_time(NaiveTime::MIN).and_utc();
+            errors.extend(self.rules.check_event_date("start_date", start, Utc::now()));
+        }
+        if let Some(end_date) = recurrence.end_date {
+            errors.extend(self.rules.check_day("end_date", end_date));
+            if recurrence.start_date.is_some_and(|start_date| end_date < start_date) {
+                errors.push(FieldError::new("end_date", "must not be before start_date"));
+            }
+        }
+        if let (Some(credit), Some(debit)) = (&credit, &debit) {
+            errors.extend(self.rules.check_accounts(credit, debit));
+        }
+        if !errors.is_empty() {
+            return Err(RepositoryError::InvalidFields(errors));
+        }
+
+        let mut recurrence_dto: dto::Recurrence = dto::DtoModelNoRef::from_model(recurrence);
+        recurrence_dto.credit_id = credit.and_then(|account| account.id).unwrap_or(-1);
+        recurrence_dto.debit_id = debit.and_then(|account| account.id).unwrap_or(-1);
+
+        Ok(recurrence_dto)
+    }
+
+    /// Builds the recurrence model, fetching its credit and debit accounts.
+    async fn recurrence_from_dto(
+        &self,
+        recurrence_dto: &dto::Recurrence,
+    ) -> Result<model::recurrence::Recurrence, RepositoryError> {
+        let credit_account = self.get_account(recurrence_dto.credit_id).await?;
+        let debit_account = self.get_account(recurrence_dto.debit_id).await?;
+
+        let mut recurrence = dto::DtoModelNoRef::to_model(recurrence_dto);
+        recurrence.credit = credit_account;
+        recurrence.debit = debit_account;
+        Ok(recurrence)
+    }
+
+    /// Builds the budget DTO once its account is resolved and checked.
+    async fn budget_to_dto(
+        &self,
+        budget: &model::budget::Budget,
+    ) -> Result<dto::Budget, RepositoryError> {
+        let mut budget_dto: dto::Budget = dto::DtoModelNoRef::from_model(budget);
+        budget_dto.account_id = self.account_id_by_path(budget.account.reference()).await?;
+
+        let checked = model::budget::Budget {
+            account: self.get_account(budget_dto.account_id).await?,
+            ..budget.clone()
+        };
+        checked.check().map_err(RepositoryError::Validation)?;
+
+        Ok(budget_dto)
+    }
+
+    /// Builds the budget model, fetching its account.
+    async fn budget_from_dto(
+        &self,
+        budget_dto: &dto::Budget,
+    ) -> Result<model::budget::Budget, RepositoryError> {
+        let mut budget = dto::DtoModelNoRef::to_model(budget_dto);
+        budget.account = self.get_account(budget_dto.account_id).await?;
+        Ok(budget)
+    }
+
+    /// Builds the transfer DTO once its envelopes are resolved and checked, on the
+    /// first day of its month.
+    async fn envelope_transfer_to_dto(
+        &self,
+        transfer: &model::envelope::EnvelopeTransfer,
+    ) -> Result<dto::EnvelopeTransfer, RepositoryError> {
+        let mut transfer_dto: dto::EnvelopeTransfer = dto::DtoModelNoRef::from_model(transfer);
+        transfer_dto.month = model::recurrence::Frequence::Monthly.period_start(transfer.month);
+
+        let mut checked = transfer.clone();
+        if let Some(from) = &transfer.from {
+            let id = self.account_id_by_path(from.reference()).await?;
+            transfer_dto.source = Some(id);
+            checked.from = Some(self.get_account(id).await?);
+        }
+        if let Some(to) = &transfer.to {
+            let id = self.account_id_by_path(to.reference()).await?;
+            transfer_dto.target = Some(id);
+            checked.to = Some(self.get_account(id).await?);
+        }
+        checked.check().map_err(RepositoryError::Validation)?;
+
+        for envelope_id in self.dao.get_envelope_account_ids().await? {
+            let envelope = self.get_account(envelope_id).await?;
+            for account in checked.from.iter().chain(&checked.to) {
+                if model::envelope::nested(account, &envelope) {
+                    return Err(RepositoryError::Validation(format!(
+                        "Account '{}' is nested with envelope '{}', spending would count twice",
+                        account.reference(),
+                        envelope.reference()
+                    )));
+                }
+            }
+        }
+
+        Ok(transfer_dto)
+    }
+
+    /// Builds the transfer model, fetching its envelopes.
+    async fn envelope_transfer_from_dto(
+        &self,
+        transfer_dto: &dto::EnvelopeTransfer,
+    ) -> Result<model::envelope::EnvelopeTransfer, RepositoryError> {
+        let mut transfer = dto::DtoModelNoRef::to_model(transfer_dto);
+        if let Some(source) = transfer_dto.source {
+            transfer.from = Some(self.get_account(source).await?);
+        }
+        if let Some(target) = transfer_dto.target {
+            transfer.to = Some(self.get_account(target).await?);
+        }
+        Ok(transfer)
+    }
+
+    /// The validation every write goes through, entries, transactions and recurrence
+    /// occurrences alike: balanced postings, event date window and account rules.
+    /// Returns the transaction DTO, account paths resolved to their ids, or every
+    /// invalid field at once along with the `errors` found by the caller.
+    /// `account_field` names the account field of each posting in errors.
+    async fn validate_transaction(
+        &self,
+        transaction: &model::transaction::Transaction,
+        stored_date: Option<DateTime<Utc>>,
+        account_field: fn(usize) -> String,
+        mut errors: Vec<FieldError>,
+    ) -> Result<dto::Transaction, RepositoryError> {
+        if let Err(message) = transaction.check_balanced() {
+            errors.push(FieldError::new("postings", message));
+        }
+        errors.extend(self.check_event_date(transaction.event_date, stored_date));
+
+        let mut transaction_dto: dto::Transaction = dto::DtoModelNoRef::from_model(transaction);
+        let mut resolved = vec![];
+        for (index, (posting_dto, posting)) in transaction_dto
+            .postings
+            .iter_mut()
+            .zip(&transaction.postings)
+            .enumerate()
+        {
+            let field = account_field(index);
+            let account = self
+                .resolve_account(&field, posting.account.reference(), &mut errors)
+                .await?;
+            posting_dto.account_id = account.as_ref().and_then(|account| account.id).unwrap_or(-1);
+            if let Some(account) = account {
+                resolved.push((field, account, posting.amount));
+            }
+        }
+        if resolved.len() == transaction.postings.len() {
+            let postings: Vec<(String, &model::account::Account, Money)> = resolved
+                .iter()
+                .map(|(field, account, amount)| (field.clone(), account, *amount))
+                .collect();
+            errors.extend(
+                self.rules
+                    .check_postings(&postings, transaction.reverses.is_none()),
+            );
+        }
+
+        match errors.is_empty() {
+            true => Ok(transaction_dto),
+            false => Err(RepositoryError::InvalidFields(errors)),
+        }
+    }
+
+    /// Builds the transaction model, fetching the account of every posting.
+    async fn transaction_from_dto(
+        &self,
+        transaction_dto: &dto::Transaction,
+    ) -> Result<model::transaction::Transaction, RepositoryError> {
+        let mut transaction = dto::DtoModelNoRef::to_model(transaction_dto);
+        for (posting, posting_dto) in transaction
+            .postings
+            .iter_mut()
+            .zip(&transaction_dto.postings)
+        {
+            posting.account = self.get_account(posting_dto.account_id).await?;
+        }
+        Ok(transaction)
+    }
+
+    /// Builds the entry model, fetching its credit and debit accounts.
+    async fn entry_from_dto(
+        &self,
+        entry_dto: &dto::Entry,
+    ) -> Result<model::entry::Entry, RepositoryError> {
+        let credit_account = self.get_account(entry_dto.credit_id).await?;
+        let debit_account = self.get_account(entry_dto.debit_id).await?;
+
+        let mut entry = dto::DtoModelNoRef::to_model(entry_dto);
+        entry.credit = credit_account;
+        entry.debit = debit_account;
+        Ok(entry)
     }
 }
 
+/// Channel notified by `notify_accounts()` on every account change.
+const ACCOUNTS_CHANNEL: &str = "accounts";
+
+/// Channel notified by `notify_entries()` on every entry change.
+const ENTRIES_CHANNEL: &str = "entries";
+
+/// Channel notified by `notify_recurrences()` on every recurrence change.
+const RECURRENCES_CHANNEL: &str = "recurrences";
+
+/// Live changes published to the `/events` stream subscribers.
+pub type LedgerEvents = broadcast::Sender<model::event::LedgerEvent>;
+
 pub struct RepositoryRealtimeUpdater {
     shared_repository: Arc<Mutex<Repository>>,
+    events: LedgerEvents,
 }
 
 impl RepositoryRealtimeUpdater {
-    pub fn new(repository: Arc<Mutex<Repository>>) -> Self {
+    pub fn new(repository: Arc<Mutex<Repository>>, events: LedgerEvents) -> Self {
         Self {
             shared_repository: repository,
+            events,
         }
     }
 
@@ -138,27 +1254,233 @@ impl RepositoryRealtimeUpdater {
         T::TlsConnect: Sync + Send,
         <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
     {
-        let updater = AccountRepositoryRealtimeUpdater::new(self.shared_repository.clone());
-        let connect = DatabaseListener::new(pg_config, tls);
-        connect.attach(updater, "todo").await;
+        let accounts = AccountRepositoryRealtimeUpdater {
+            shared_repository: self.shared_repository.clone(),
+            events: self.events.clone(),
+        };
+        let entries = EntryRealtimeUpdater {
+            shared_repository: self.shared_repository.clone(),
+            events: self.events.clone(),
+        };
+        let recurrences = RecurrenceRealtimeUpdater {
+            shared_repository: self.shared_repository.clone(),
+        };
+
+        DatabaseListener::new(pg_config, tls)
+            .subscribe(ACCOUNTS_CHANNEL, accounts)
+            .subscribe(ENTRIES_CHANNEL, entries)
+            .subscribe(RECURRENCES_CHANNEL, recurrences)
+            .listen()
+            .await;
     }
 }
 
-pub struct AccountRepositoryRealtimeUpdater {
-    _shared_repository: Arc<Mutex<Repository>>,
+/// Background task posting the entries of due recurrences.
+pub struct RecurrenceScheduler {
+    shared_repository: Arc<Mutex<Repository>>,
 }
 
-impl AccountRepositoryRealtimeUpdater {
-    fn new(shared_repository: Arc<Mutex<Repository>>) -> Self {
+impl RecurrenceScheduler {
+    pub fn new(repository: Arc<Mutex<Repository>>) -> Self {
         Self {
-            _shared_repository: shared_repository,
+            shared_repository: repository,
         }
     }
+
+    /// Materializes due occurrences right away, then once every `period`.
+    #[instrument(name = "recurrence_scheduler", level = Level::DEBUG, skip(self))]
+    pub async fn run(&self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+
+            let today = Utc::now().date_naive();
+            let res = self
+                .shared_repository
+                .lock()
+                .await
+                .materialize_recurrences(today)
+                .await;
+            match res {
+                Ok(created) => {
+                    tracing::event!(Level::DEBUG, "{} recurring entries posted", created)
+                }
+                Err(e) => tracing::event!(Level::ERROR, "Failed to post recurring entries: {}", e),
+            }
+        }
+    }
+}
+
+pub struct AccountRepositoryRealtimeUpdater {
+    shared_repository: Arc<Mutex<Repository>>,
+    events: LedgerEvents,
 }
 
 impl NotificationHandler for AccountRepositoryRealtimeUpdater {
-    fn on_notification_received(&self, _channel: &str, _message: &str) {
-        todo!("TO BE IMPLEMENTED")
+    type Event = dto::AccountChange;
+
+    async fn on_notification_received(&self, change: dto::AccountChange) {
+        if change.operation == dto::Operation::Insert {
+            let account_dto = dto::Account {
+                id: change.id,
+                name: change.name.clone(),
+                family: change.family,
+                parent: change.parent,
+            };
+            // No subscriber is not an error
+            let _ = self.events.send(model::event::LedgerEvent::AccountCreated {
+                id: change.id,
+                account: account_dto.to_model(),
+            });
+        }
+
+        let res = self
+            .shared_repository
+            .lock()
+            .await
+            .apply_account_change(change)
+            .await;
+        if let Err(e) = res {
+            tracing::event!(Level::ERROR, "Failed to apply account change: {}", e);
+        }
+    }
+
+    async fn on_resync_required(&self) {
+        tracing::event!(Level::INFO, "Reloading account cache after reconnection");
+        let res = self
+            .shared_repository
+            .lock()
+            .await
+            .reload_accounts()
+            .await;
+        if let Err(e) = res {
+            tracing::event!(Level::ERROR, "Failed to reload account cache: {}", e);
+        }
+    }
+}
+
+/// Publishes entry changes and the balances they move to the `/events` stream.
+pub struct EntryRealtimeUpdater {
+    shared_repository: Arc<Mutex<Repository>>,
+    events: LedgerEvents,
+}
+
+impl EntryRealtimeUpdater {
+    /// The changed entry followed by the balances of every account it was and is
+    /// posted to, in a single stream order.
+    async fn entry_events(
+        &self,
+        change: &dto::EntryChange,
+    ) -> Result<Vec<model::event::LedgerEvent>, RepositoryError> {
+        let repository = self.shared_repository.lock().await;
+        let mut events = vec![];
+        let mut accounts = change.accounts.clone();
+
+        let transaction = match change.operation {
+            dto::Operation::Delete => None,
+            _ => repository.find_transaction(change.id).await?,
+        };
+        if let Some(transaction) = transaction {
+            let postings: Vec<model::event::Movement> = transaction
+                .postings
+                .iter()
+                .filter_map(|posting| {
+                    Some(model::event::Movement {
+                        account: posting.account.id?,
+                        amount: posting.amount,
+                    })
+                })
+                .collect();
+            accounts.extend(postings.iter().map(|movement| movement.account));
+
+            events.push(match change.operation {
+                dto::Operation::Insert => model::event::LedgerEvent::EntryCreated {
+                    id: change.id,
+                    postings,
+                },
+                _ => model::event::LedgerEvent::EntryUpdated {
+                    id: change.id,
+                    postings,
+                },
+            });
+        }
+
+        accounts.sort();
+        accounts.dedup();
+        for account in accounts {
+            let balance = repository.get_account_balance(account).await?;
+            if let Some(balance) = balance {
+                events.push(model::event::LedgerEvent::BalanceChanged {
+                    account,
+                    balance: balance.balance,
+                });
+            }
+        }
+        Ok(events)
+    }
+}
+
+impl NotificationHandler for EntryRealtimeUpdater {
+    type Event = dto::EntryChange;
+
+    async fn on_notification_received(&self, change: dto::EntryChange) {
+        // Balances are only worth querying when someone is listening
+        if self.events.receiver_count() == 0 {
+            return;
+        }
+
+        match self.entry_events(&change).await {
+            Ok(events) => {
+                for event in events {
+                    let _ = self.events.send(event);
+                }
+            }
+            Err(e) => tracing::event!(Level::ERROR, "Failed to fetch changed entry: {}", e),
+        }
+    }
+
+    async fn on_resync_required(&self) {
+        // Nothing derived is kept, subscribers see the changes that follow
+    }
+}
+
+/// Posts the due entries of a recurrence as soon as it is created or changed,
+/// instead of waiting for the next `RecurrenceScheduler` run.
+pub struct RecurrenceRealtimeUpdater {
+    shared_repository: Arc<Mutex<Repository>>,
+}
+
+impl NotificationHandler for RecurrenceRealtimeUpdater {
+    type Event = dto::RecurrenceChange;
+
+    async fn on_notification_received(&self, change: dto::RecurrenceChange) {
+        if change.operation == dto::Operation::Delete {
+            return;
+        }
+
+        let today = Utc::now().date_naive();
+        let res = self
+            .shared_repository
+            .lock()
+            .await
+            .materialize_recurrence(change.id, today)
+            .await;
+        if let Err(e) = res {
+            tracing::event!(Level::ERROR, "Failed to post recurring entries: {}", e);
+        }
+    }
+
+    async fn on_resync_required(&self) {
+        let today = Utc::now().date_naive();
+        let res = self
+            .shared_repository
+            .lock()
+            .await
+            .materialize_recurrences(today)
+            .await;
+        if let Err(e) = res {
+            tracing::event!(Level::ERROR, "Failed to post recurring entries: {}", e);
+        }
     }
 }
 
@@ -166,9 +1488,59 @@ impl NotificationHandler for AccountRepositoryRealtimeUpdater {
 async fn initialize_account_repository(dao: &dao::Dao) -> cache::AccountRepository {
     let cache = cache::AccountRepository::new();
     let accounts = dao.get_accounts().await.expect("Failed to fetch accounts");
-    for account in accounts {
-        let _ = cache.add(account.id, account.to_model()).await;
-    }
+    cache
+        .add_all(accounts.iter().map(|account| account.to_model()))
+        .await;
 
     cache
 }
+
+/// Account field of a transaction posting, in validation errors.
+fn posting_field(index: usize) -> String {
+    format!("postings[{}].account", index)
+}
+
+/// Account field of an entry posting, in validation errors: entries credit their first
+/// posting and debit the second one, see `model::transaction::Transaction::from`.
+fn entry_field(index: usize) -> String {
+    match index {
+        0 => "credit".to_string(),
+        _ => "debit".to_string(),
+    }
+}
+
+/// First instant of the day after `date`, the exclusive end of a period ending on it.
+fn day_after(date: NaiveDate) -> Result<DateTime<Utc>, RepositoryError> {
+    date.checked_add_days(Days::new(1))
+        .map(|day| day.and_time(NaiveTime::MIN).and_utc())
+        .ok_or_else(|| out_of_range(date))
+}
+
+fn out_of_range(date: NaiveDate) -> RepositoryError {
+    RepositoryError::Validation(format!("Date {} is out of range", date))
+}
+
+/// Entries dated within the period, from the first instant of its first day
+/// to the last instant of its last day.
+fn period_filters(period: &model::report::ReportPeriod) -> filter::Filters<filter::EntryFields> {
+    days_filters(period.start_date, period.end_date)
+}
+
+/// Entries from the first day to the last one, both inclusive.
+fn days_filters(
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> filter::Filters<filter::EntryFields> {
+    let start = start_date.and_time(NaiveTime::MIN).and_utc();
+    let end = (end_date + Days::new(1)).and_time(NaiveTime::MIN).and_utc();
+
+    let mut filters = filter::Filters::new();
+    filters
+        .and(
+            &filter::EntryFields::EventDate,
+            filter::Operator::GreaterThanOrEqual,
+            start,
+        )
+        .and(&filter::EntryFields::EventDate, filter::Operator::LessThan, end);
+    filters
+}
@@ -1,28 +1,33 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
-use crate::model;
+use crate::{model, repository::error::RepositoryError};
 
 pub trait Repository<K, V>
 where
     K: Eq + std::hash::Hash + Copy,
 {
-    async fn get(&self, key: &K) -> Result<Option<V>, Box<dyn std::error::Error>>;
-    async fn add(&self, key: K, value: V) -> Result<(), Box<dyn std::error::Error>>;
+    async fn get(&self, key: &K) -> Result<Option<V>, RepositoryError>;
+    async fn add(&self, key: K, value: V) -> Result<(), RepositoryError>;
+    async fn remove(&self, key: &K) -> Result<Option<V>, RepositoryError>;
 }
 
 type MapCache<K, V> = Arc<Mutex<HashMap<K, V>>>;
 
 pub struct AccountRepository {
     cache: MapCache<i32, model::account::Account>,
+    /// Account ids by path. Renaming or moving an account changes the paths of all its
+    /// sub-accounts, the subtree of a changed account is indexed again.
+    paths: MapCache<String, i32>,
 }
 
 impl AccountRepository {
     pub fn new() -> Self {
         AccountRepository {
             cache: Arc::new(Mutex::new(HashMap::new())),
+            paths: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
@@ -31,7 +36,7 @@ impl Repository<i32, model::account::Account> for AccountRepository {
     async fn get(
         &self,
         key: &i32,
-    ) -> Result<Option<model::account::Account>, Box<dyn std::error::Error>> {
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
         let cache = self.cache.lock().unwrap();
         Ok(cache.get(key).cloned())
     }
@@ -40,24 +45,194 @@ impl Repository<i32, model::account::Account> for AccountRepository {
         &self,
         key: i32,
         value: model::account::Account,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), RepositoryError> {
         let mut cache = self.cache.lock().unwrap();
-        cache.insert(key, value);
+        let mut paths = self.paths.lock().unwrap();
+        if let Some(previous) = cache.insert(key, value) {
+            unindex_path(&mut paths, key, previous.path);
+        }
+        index_subtree(&mut cache, &mut paths, key);
         Ok(())
     }
+
+    async fn remove(
+        &self,
+        key: &i32,
+    ) -> Result<Option<model::account::Account>, RepositoryError> {
+        let mut cache = self.cache.lock().unwrap();
+        let mut paths = self.paths.lock().unwrap();
+        let removed = cache.remove(key);
+        if let Some(removed) = &removed {
+            unindex_path(&mut paths, *key, removed.path.clone());
+        }
+        // Sub-accounts left behind lose their path until their parent is back
+        index_subtree(&mut cache, &mut paths, *key);
+        Ok(removed)
+    }
 }
 
 impl AccountRepository {
-    pub async fn get_id_by_name(
-        &self,
-        name: &str,
-    ) -> Result<i32, Box<dyn std::error::Error>> {
-        let cache = self.cache.lock().unwrap();
-        for (key, value) in cache.iter() {
-            if value.name == name {
-                return Ok(*key);
+    /// Drops every cached account, before reloading them all.
+    pub async fn clear(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.clear();
+        self.paths.lock().unwrap().clear();
+    }
+
+    /// Caches many accounts at once, their paths are indexed only once all are in.
+    pub async fn add_all(&self, accounts: impl IntoIterator<Item = model::account::Account>) {
+        let mut cache = self.cache.lock().unwrap();
+        for account in accounts {
+            if let Some(id) = account.id {
+                cache.insert(id, account);
+            }
+        }
+        index_paths(&mut cache, &mut self.paths.lock().unwrap());
+    }
+
+    /// Returns `None` when no account has this path, see `model::account::Account::path`.
+    pub async fn get_id_by_path(&self, path: &str) -> Option<i32> {
+        let paths = self.paths.lock().unwrap();
+        paths.get(path).copied()
+    }
+}
+
+/// Sets the path of every cached account and rebuilds the index of their ids by path.
+fn index_paths(
+    cache: &mut HashMap<i32, model::account::Account>,
+    paths: &mut HashMap<String, i32>,
+) {
+    let computed: Vec<(i32, Option<String>)> =
+        cache.keys().map(|id| (*id, path_of(cache, *id))).collect();
+
+    paths.clear();
+    for (id, path) in computed {
+        if let Some(path) = &path {
+            paths.insert(path.clone(), id);
+        }
+        if let Some(account) = cache.get_mut(&id) {
+            account.path = path;
+        }
+    }
+}
+
+/// Sets the path of `root` and of all its sub-accounts, and indexes their ids by path.
+fn index_subtree(
+    cache: &mut HashMap<i32, model::account::Account>,
+    paths: &mut HashMap<String, i32>,
+    root: i32,
+) {
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (id, account) in cache.iter() {
+        if let Some(parent_id) = account.parent_id {
+            children.entry(parent_id).or_default().push(*id);
+        }
+    }
+
+    let mut pending = vec![root];
+    let mut visited = HashSet::new();
+    while let Some(id) = pending.pop() {
+        // The database forbids cycles, only a stale cache could hold one
+        if !visited.insert(id) {
+            continue;
+        }
+        let path = path_of(cache, id);
+        if let Some(account) = cache.get_mut(&id) {
+            unindex_path(paths, id, account.path.take());
+            if let Some(path) = &path {
+                paths.insert(path.clone(), id);
             }
+            account.path = path;
+        }
+        pending.extend(children.get(&id).into_iter().flatten());
+    }
+}
+
+/// Drops the former path of an account, unless another account took it over meanwhile.
+fn unindex_path(paths: &mut HashMap<String, i32>, id: i32, path: Option<String>) {
+    if let Some(path) = path.filter(|path| paths.get(path) == Some(&id)) {
+        paths.remove(&path);
+    }
+}
+
+/// Returns `None` while an ancestor of the account is not cached yet.
+fn path_of(cache: &HashMap<i32, model::account::Account>, id: i32) -> Option<String> {
+    let mut names = vec![];
+    let mut current = Some(id);
+    while let Some(id) = current {
+        // The database forbids cycles, only a stale cache could hold one
+        if names.len() > cache.len() {
+            return None;
+        }
+        let account = cache.get(&id)?;
+        names.push(account.name.as_str());
+        current = account.parent_id;
+    }
+
+    names.reverse();
+    Some(names.join(model::account::Account::PATH_SEPARATOR))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::account::{Account, AccountFamily};
+
+    fn account(id: i32, name: &str, parent_id: Option<i32>) -> Account {
+        Account {
+            id: Some(id),
+            name: name.to_string(),
+            family: AccountFamily::Expense,
+            parent_id,
+            path: None,
         }
-        Err("Account not found".into())
     }
-}
\ No newline at end of file
+
+    async fn path(cache: &AccountRepository, id: i32) -> Option<String> {
+        cache.get(&id).await.unwrap().and_then(|account| account.path)
+    }
+
+    #[tokio::test]
+    async fn test_paths_follow_account_changes() {
+        let cache = AccountRepository::new();
+        // Sub-accounts listed before their parent get their path once it is in
+        cache
+            .add_all(vec![
+                account(3, "Internet", Some(2)),
+                account(2, "Services", Some(1)),
+                account(1, "Home", None),
+                account(4, "Food", None),
+            ])
+            .await;
+        assert_eq!(path(&cache, 3).await.as_deref(), Some("Home:Services:Internet"));
+        assert_eq!(cache.get_id_by_path("Home:Services:Internet").await, Some(3));
+        assert_eq!(cache.get_id_by_path("Internet").await, None);
+
+        // Added one at a time
+        cache.add(5, account(5, "Phone", Some(2))).await.unwrap();
+        assert_eq!(cache.get_id_by_path("Home:Services:Phone").await, Some(5));
+
+        // Renaming an account renames its whole subtree, the former paths are gone
+        cache.add(2, account(2, "Utilities", Some(1))).await.unwrap();
+        assert_eq!(cache.get_id_by_path("Home:Utilities:Internet").await, Some(3));
+        assert_eq!(cache.get_id_by_path("Home:Utilities:Phone").await, Some(5));
+        assert_eq!(cache.get_id_by_path("Home:Services").await, None);
+        assert_eq!(cache.get_id_by_path("Home:Services:Internet").await, None);
+
+        // Moving it under another parent
+        cache.add(2, account(2, "Utilities", Some(4))).await.unwrap();
+        assert_eq!(path(&cache, 3).await.as_deref(), Some("Food:Utilities:Internet"));
+        assert_eq!(cache.get_id_by_path("Home:Utilities:Internet").await, None);
+        assert_eq!(cache.get_id_by_path("Home").await, Some(1));
+
+        // Removing an ancestor leaves its sub-accounts without a path until it is back
+        let removed = cache.remove(&2).await.unwrap();
+        assert_eq!(removed.and_then(|account| account.path).as_deref(), Some("Food:Utilities"));
+        assert_eq!(cache.get_id_by_path("Food:Utilities").await, None);
+        assert_eq!(cache.get_id_by_path("Food:Utilities:Internet").await, None);
+        assert_eq!(path(&cache, 3).await, None);
+        cache.add(2, account(2, "Utilities", None)).await.unwrap();
+        assert_eq!(cache.get_id_by_path("Utilities:Internet").await, Some(3));
+        assert_eq!(cache.get_id_by_path("Food").await, Some(4));
+    }
+}
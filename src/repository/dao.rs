@@ -1,114 +1,933 @@
-use std::error::Error;
-
+use chrono::{DateTime, NaiveDate, Utc};
+use tokio_postgres::{Row, error::SqlState};
 use tracing::Level;
 
-use crate::repository::{dto, filter};
+use crate::model::money::Money;
+use crate::repository::{
+    dto,
+    error::RepositoryError,
+    filter::{self, ToSql},
+};
 
 pub(super) struct Dao {
     pub pool: deadpool_postgres::Pool,
 }
 
 pub(super) fn new(pool: deadpool_postgres::Pool) -> Dao {
-    Dao { pool: pool }
+    Dao { pool }
 }
 
 impl Dao {
     pub(super) async fn insert_account(
         &self,
         account: &dto::Account,
-    ) -> Result<i32, Box<dyn Error>> {
-        let query = "INSERT INTO accounts (name, family) VALUES ($1, $2) RETURNING id";
+    ) -> Result<i32, RepositoryError> {
+        let query = "INSERT INTO accounts (name, family, parent) VALUES ($1, $2, $3) RETURNING id";
         let client = self.pool.get().await?;
         let row = client
-            .query_one(query, &[&account.name, &account.family])
+            .query_one(query, &[&account.name, &account.family, &account.parent])
             .await?;
         Ok(row.get(0))
     }
 
-    pub(super) async fn get_account(&self, id: i32) -> Result<dto::Account, Box<dyn Error>> {
-        let query = "SELECT id, name, family FROM accounts WHERE id = $1";
+    pub(super) async fn get_account(
+        &self,
+        id: i32,
+    ) -> Result<Option<dto::Account>, RepositoryError> {
+        let query = "SELECT id, name, family, parent FROM accounts WHERE id = $1";
         let client = self.pool.get().await?;
-        let row = client.query_one(query, &[&id]).await?;
-        Ok(dto::Account {
+        let row = client.query_opt(query, &[&id]).await?;
+        Ok(row.as_ref().map(account_from_row))
+    }
+
+    pub(super) async fn get_accounts(&self) -> Result<Vec<dto::Account>, RepositoryError> {
+        let query = "SELECT id, name, family, parent FROM accounts ORDER BY id";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[]).await?;
+        let accounts: Vec<dto::Account> = rows.iter().map(account_from_row).collect();
+
+        tracing::event!(Level::DEBUG, "Retrieved {:?}", accounts);
+        Ok(accounts)
+    }
+
+    /// Updates the given columns of an account, `None` keeps the current value.
+    /// `Some(None)` as parent moves the account to the top level.
+    /// Returns `None` when no account has this id.
+    pub(super) async fn update_account(
+        &self,
+        id: i32,
+        name: Option<&str>,
+        family: Option<i32>,
+        parent: Option<Option<i32>>,
+    ) -> Result<Option<dto::Account>, RepositoryError> {
+        let query = "UPDATE accounts SET name = COALESCE($2, name), family = COALESCE($3, family), \
+            parent = CASE WHEN $4 THEN $5 ELSE parent END \
+            WHERE id = $1 RETURNING id, name, family, parent";
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                query,
+                &[&id, &name, &family, &parent.is_some(), &parent.flatten()],
+            )
+            .await?;
+        Ok(row.as_ref().map(account_from_row))
+    }
+
+    /// Returns the number of deleted rows, 0 when no account has this id.
+    /// Fails with `RepositoryError::InUse` when entries still reference the account.
+    pub(super) async fn delete_account(&self, id: i32) -> Result<u64, RepositoryError> {
+        let query = "DELETE FROM accounts WHERE id = $1";
+        let client = self.pool.get().await?;
+        match client.execute(query, &[&id]).await {
+            Ok(deleted) => Ok(deleted),
+            Err(e) if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) => {
+                Err(RepositoryError::InUse(format!(
+                    "Account {} is still referenced by entries or sub-accounts",
+                    id
+                )))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(super) async fn get_account_balance(
+        &self,
+        id: i32,
+    ) -> Result<Option<dto::AccountBalance>, RepositoryError> {
+        let query = "SELECT id, balance FROM account_balances WHERE id = $1";
+        let client = self.pool.get().await?;
+        let row = client.query_opt(query, &[&id]).await?;
+        Ok(row.map(|row| dto::AccountBalance {
             id: row.get(0),
-            name: row.get(1),
-            family: row.get(2),
-        })
+            balance: row.get(1),
+        }))
     }
 
-    pub(super) async fn get_accounts(&self) -> Result<Vec<dto::Account>, Box<dyn Error>> {
-        let query = "SELECT id, name, family FROM accounts";
+    pub(super) async fn get_account_balances(
+        &self,
+    ) -> Result<Vec<dto::AccountBalance>, RepositoryError> {
+        let query = "SELECT id, balance FROM account_balances ORDER BY id";
         let client = self.pool.get().await?;
         let rows = client.query(query, &[]).await?;
-        let accounts: Vec<dto::Account> = rows
+        let balances: Vec<dto::AccountBalance> = rows
             .iter()
-            .map(|row| dto::Account {
+            .map(|row| dto::AccountBalance {
                 id: row.get(0),
-                name: row.get(1),
-                family: row.get(2),
+                balance: row.get(1),
             })
             .collect();
+        Ok(balances)
+    }
 
-        tracing::event!(Level::DEBUG, "Retrieved {:?}", accounts);
-        Ok(accounts)
+    /// Balance of every account over the entries matching the filters, meant for
+    /// `EntryFields::EventDate` conditions.
+    pub(super) async fn get_filtered_account_balances(
+        &self,
+        filters: &filter::Filters<filter::EntryFields>,
+    ) -> Result<Vec<dto::AccountBalance>, RepositoryError> {
+        let filter = filters.build();
+        let query = format!(
+            "SELECT accounts.id, COALESCE(sum(movements.amount), 0.0) \
+            FROM accounts LEFT OUTER JOIN ({}) AS movements ON movements.account_id = accounts.id \
+            GROUP BY accounts.id ORDER BY accounts.id",
+            movements_query(&filter)
+        );
+        let client = self.pool.get().await?;
+        let rows = client.query(&query, &filter.sql_parameters()).await?;
+        let balances: Vec<dto::AccountBalance> = rows
+            .iter()
+            .map(|row| dto::AccountBalance {
+                id: row.get(0),
+                balance: row.get(1),
+            })
+            .collect();
+        Ok(balances)
     }
 
-    pub(super) async fn insert_entry(&self, entry: &dto::Entry) -> Result<i32, Box<dyn Error>> {
-        let query = "INSERT INTO entries (description, amount::double precision, event_date, credit, debit) VALUES ($1, $2, $3, $4, $5) RETURNING id";
+    /// Amounts debited from and credited to every account over the entries matching
+    /// the filters, meant for `EntryFields::EventDate` conditions.
+    pub(super) async fn get_trial_balance_lines(
+        &self,
+        filters: &filter::Filters<filter::EntryFields>,
+    ) -> Result<Vec<dto::TrialBalanceLine>, RepositoryError> {
+        let filter = filters.build();
+        let query = format!(
+            "SELECT accounts.id, \
+                COALESCE(sum(-movements.amount) FILTER (WHERE movements.amount < 0.0), 0.0), \
+                COALESCE(sum(movements.amount) FILTER (WHERE movements.amount > 0.0), 0.0) \
+            FROM accounts LEFT OUTER JOIN ({}) AS movements ON movements.account_id = accounts.id \
+            GROUP BY accounts.id ORDER BY accounts.id",
+            movements_query(&filter)
+        );
         let client = self.pool.get().await?;
-        let row = client
+        let rows = client.query(&query, &filter.sql_parameters()).await?;
+        let lines: Vec<dto::TrialBalanceLine> = rows
+            .iter()
+            .map(|row| dto::TrialBalanceLine {
+                account_id: row.get(0),
+                debits: row.get(1),
+                credits: row.get(2),
+            })
+            .collect();
+        Ok(lines)
+    }
+
+    /// Accounts whose balance in the `account_balances` materialized view differs from
+    /// the sum of their postings, e.g. after changes made with the triggers disabled.
+    pub(super) async fn get_balance_mismatches(
+        &self,
+    ) -> Result<Vec<dto::BalanceMismatch>, RepositoryError> {
+        let query = "SELECT accounts.id, account_balances.balance, COALESCE(fresh.balance, 0.0) \
+            FROM accounts \
+            LEFT OUTER JOIN account_balances ON account_balances.id = accounts.id \
+            LEFT OUTER JOIN ( \
+                SELECT account_id, sum(amount) AS balance FROM account_ledgers GROUP BY account_id \
+            ) AS fresh ON fresh.account_id = accounts.id \
+            WHERE account_balances.balance IS DISTINCT FROM COALESCE(fresh.balance, 0.0) \
+            ORDER BY accounts.id";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[]).await?;
+        let mismatches: Vec<dto::BalanceMismatch> = rows
+            .iter()
+            .map(|row| dto::BalanceMismatch {
+                account_id: row.get(0),
+                recorded: row.get(1),
+                computed: row.get(2),
+            })
+            .collect();
+        Ok(mismatches)
+    }
+
+    /// Money moved between the `cash_ids` accounts and every other account, by
+    /// counter-account. Inflows leave the counter-account, outflows reach it.
+    /// Postings between two cash accounts are left out.
+    pub(super) async fn get_cash_flows(
+        &self,
+        filters: &filter::Filters<filter::EntryFields>,
+        cash_ids: &[i32],
+    ) -> Result<Vec<dto::CashFlow>, RepositoryError> {
+        let filter = filters.build();
+        let condition = if filter.condition.is_empty() {
+            "TRUE"
+        } else {
+            &filter.condition
+        };
+        let mut parameters = filter.sql_parameters();
+        let query = format!(
+            "SELECT counter.account_id, \
+                COALESCE(sum(-counter.amount) FILTER (WHERE counter.amount < 0.0), 0.0), \
+                COALESCE(sum(counter.amount) FILTER (WHERE counter.amount > 0.0), 0.0) \
+            FROM account_ledgers AS counter JOIN entries ON entries.id = counter.entry_id \
+            WHERE ({}) AND counter.account_id <> ALL(${n}) AND EXISTS ( \
+                SELECT 1 FROM account_ledgers AS cash \
+                WHERE cash.entry_id = counter.entry_id AND cash.account_id = ANY(${n}) \
+            ) \
+            GROUP BY counter.account_id ORDER BY counter.account_id",
+            condition,
+            n = parameters.len() + 1,
+        );
+        parameters.push(&cash_ids);
+        let client = self.pool.get().await?;
+        let rows = client.query(&query, &parameters).await?;
+        let flows: Vec<dto::CashFlow> = rows
+            .iter()
+            .map(|row| dto::CashFlow {
+                account_id: row.get(0),
+                inflow: row.get(1),
+                outflow: row.get(2),
+            })
+            .collect();
+        Ok(flows)
+    }
+
+    /// Sum of the postings of each family over each period, `ends` being the sorted
+    /// exclusive ends of the periods. The first period also gets every posting before
+    /// it, so that summing the periods up to one gives the balances at its end.
+    /// A single scan of the postings whatever the number of periods.
+    pub(super) async fn get_family_movements(
+        &self,
+        ends: &[DateTime<Utc>],
+    ) -> Result<Vec<dto::FamilyMovement>, RepositoryError> {
+        let Some(last_end) = ends.last() else {
+            return Ok(vec![]);
+        };
+        // width_bucket counts the ends up to the event date, the index of its period
+        let query = "SELECT width_bucket(entries.event_date, $1::timestamptz[]) AS period, \
+                accounts.family, sum(account_ledgers.amount) \
+            FROM account_ledgers \
+            JOIN entries ON entries.id = account_ledgers.entry_id \
+            JOIN accounts ON accounts.id = account_ledgers.account_id \
+            WHERE entries.event_date < $2 \
+            GROUP BY period, accounts.family ORDER BY period, accounts.family";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[&ends, last_end]).await?;
+        let movements: Vec<dto::FamilyMovement> = rows
+            .iter()
+            .map(|row| dto::FamilyMovement {
+                period: row.get(0),
+                family: row.get(1),
+                amount: row.get(2),
+            })
+            .collect();
+        Ok(movements)
+    }
+
+    /// Balance of the account and of all its sub-accounts.
+    pub(super) async fn get_account_subtotal(&self, id: i32) -> Result<Money, RepositoryError> {
+        let query = "WITH RECURSIVE subtree(id) AS ( \
+                SELECT id FROM accounts WHERE id = $1 \
+                UNION ALL \
+                SELECT accounts.id FROM accounts JOIN subtree ON accounts.parent = subtree.id \
+            ) \
+            SELECT COALESCE(sum(account_balances.balance), 0.0) \
+            FROM subtree JOIN account_balances ON account_balances.id = subtree.id";
+        let client = self.pool.get().await?;
+        let row = client.query_one(query, &[&id]).await?;
+        Ok(row.get(0))
+    }
+
+    /// Sum of the account movements strictly before the date.
+    pub(super) async fn get_ledger_balance_before(
+        &self,
+        account_id: i32,
+        date: DateTime<Utc>,
+    ) -> Result<Money, RepositoryError> {
+        let query = "SELECT COALESCE(sum(account_ledgers.amount), 0.0) \
+            FROM account_ledgers JOIN entries ON entries.id = account_ledgers.entry_id \
+            WHERE account_ledgers.account_id = $1 AND entries.event_date < $2";
+        let client = self.pool.get().await?;
+        let row = client.query_one(query, &[&account_id, &date]).await?;
+        Ok(row.get(0))
+    }
+
+    /// Movements of the account between the two dates, both inclusive, oldest first.
+    /// The counter account of a split entry is its largest posting on the other side.
+    pub(super) async fn get_ledger_lines(
+        &self,
+        account_id: i32,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+    ) -> Result<Vec<dto::LedgerLine>, RepositoryError> {
+        let query = "SELECT entries.id, entries.description, entries.event_date, \
+                account_ledgers.amount, counter.account \
+            FROM account_ledgers JOIN entries ON entries.id = account_ledgers.entry_id \
+            CROSS JOIN LATERAL ( \
+                SELECT postings.account FROM postings \
+                WHERE postings.entry = entries.id \
+                AND sign(postings.amount) <> sign(account_ledgers.amount) \
+                ORDER BY abs(postings.amount) DESC, postings.id LIMIT 1 \
+            ) counter \
+            WHERE account_ledgers.account_id = $1 \
+            AND ($2::timestamptz IS NULL OR entries.event_date >= $2) \
+            AND ($3::timestamptz IS NULL OR entries.event_date <= $3) \
+            ORDER BY entries.event_date, entries.id, account_ledgers.amount DESC";
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(query, &[&account_id, &start_date, &end_date])
+            .await?;
+        let lines: Vec<dto::LedgerLine> = rows
+            .iter()
+            .map(|row| dto::LedgerLine {
+                entry_id: row.get(0),
+                description: row.get(1),
+                event_date: row.get(2),
+                amount: row.get(3),
+                counter_account_id: row.get(4),
+            })
+            .collect();
+        Ok(lines)
+    }
+
+    /// Writes the entry and its postings at once, they are checked to balance on commit.
+    pub(super) async fn insert_transaction(
+        &self,
+        transaction: &dto::Transaction,
+    ) -> Result<i32, RepositoryError> {
+        let query = "INSERT INTO entries (description, event_date, reverses) VALUES ($1, $2, $3) RETURNING id";
+        let mut client = self.pool.get().await?;
+        let db_transaction = client.transaction().await?;
+        let row = match db_transaction
             .query_one(
                 query,
                 &[
-                    &entry.description,
-                    &entry.amount,
-                    &entry.event_date,
-                    &entry.credit_id,
-                    &entry.debit_id,
+                    &transaction.description,
+                    &transaction.event_date,
+                    &transaction.reverses,
                 ],
             )
-            .await?;
-        Ok(row.get(0))
+            .await
+        {
+            Ok(row) => row,
+            Err(e) if violated_constraint(&e) == Some("entries_reverses_key") => {
+                let id = transaction.reverses.unwrap_or(-1);
+                return Err(RepositoryError::Conflict(format!(
+                    "Entry {} has already been reversed",
+                    id
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let id: i32 = row.get(0);
+
+        insert_postings(&db_transaction, id, &transaction.postings).await?;
+        db_transaction.commit().await?;
+        Ok(id)
     }
 
-    pub(super) async fn get_entry(&self, id: i32) -> Result<dto::Entry, Box<dyn Error>> {
-        let query = "SELECT id, description, amount::double precision, event_date, credit, debit FROM entries WHERE id = $1";
+    pub(super) async fn get_transaction(
+        &self,
+        id: i32,
+    ) -> Result<Option<dto::Transaction>, RepositoryError> {
+        let query = "SELECT id, description, event_date, reverses FROM entries WHERE id = $1";
+        let postings_query = "SELECT account, amount FROM postings WHERE entry = $1 ORDER BY id";
         let client = self.pool.get().await?;
-        let row = client.query_one(query, &[&id]).await?;
-        Ok(dto::Entry {
+        let Some(row) = client.query_opt(query, &[&id]).await? else {
+            return Ok(None);
+        };
+        let postings = client.query(postings_query, &[&id]).await?;
+        Ok(Some(dto::Transaction {
             id: row.get(0),
             description: row.get(1),
-            amount: row.get(2),
-            event_date: row.get(3),
-            credit_id: row.get(4),
-            debit_id: row.get(5),
-        })
+            event_date: row.get(2),
+            reverses: row.get(3),
+            postings: postings
+                .iter()
+                .map(|row| dto::Posting {
+                    account_id: row.get(0),
+                    amount: row.get(1),
+                })
+                .collect(),
+        }))
     }
 
+    /// Replaces the postings of the entry, the reversal link is left untouched.
+    /// Returns `false` when no entry has this id.
+    pub(super) async fn update_transaction(
+        &self,
+        id: i32,
+        transaction: &dto::Transaction,
+    ) -> Result<bool, RepositoryError> {
+        let query = "UPDATE entries SET description = $2, event_date = $3 WHERE id = $1";
+        let mut client = self.pool.get().await?;
+        let db_transaction = client.transaction().await?;
+        let updated = db_transaction
+            .execute(
+                query,
+                &[&id, &transaction.description, &transaction.event_date],
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        db_transaction
+            .execute("DELETE FROM postings WHERE entry = $1", &[&id])
+            .await?;
+        insert_postings(&db_transaction, id, &transaction.postings).await?;
+        db_transaction.commit().await?;
+        Ok(true)
+    }
+
+    pub(super) async fn get_entry(&self, id: i32) -> Result<Option<dto::Entry>, RepositoryError> {
+        let query = "SELECT id, description, amount, event_date, credit, debit, reverses FROM pair_entries WHERE id = $1";
+        let client = self.pool.get().await?;
+        let row = client.query_opt(query, &[&id]).await?;
+        Ok(row.as_ref().map(entry_from_row))
+    }
+
+    /// Returns one page of the matching entries along with the total number of matches.
+    /// Split entries are left out, see `pair_entries`.
     pub(super) async fn get_entries(
         &self,
         filters: &filter::Filters<filter::EntryFields>,
-    ) -> Result<Vec<dto::Entry>, Box<dyn Error>> {
-        let mut query =
-            "SELECT id, description, amount::double precision, event_date, credit, debit FROM entries"
-                .to_string();
-        let where_clause = filters.build();
-        if !where_clause.is_empty() {
-            query = format!("{} WHERE {}", query, where_clause);
-        }
+        sort: &filter::Sort<filter::EntryFields>,
+        pagination: &filter::Pagination,
+    ) -> Result<(Vec<dto::Entry>, i64), RepositoryError> {
+        let filter = filters.build();
+        let where_clause = if filter.condition.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", filter.condition)
+        };
+        let mut parameters = filter.sql_parameters();
+
+        let client = self.pool.get().await?;
+        let count_query = format!("SELECT count(*) FROM pair_entries{}", where_clause);
+        let total: i64 = client.query_one(&count_query, &parameters).await?.get(0);
+
+        // Entry ids break ties so pages never overlap
+        let query = format!(
+            "SELECT id, description, amount, event_date, credit, debit, reverses FROM pair_entries{} ORDER BY {}, id {} LIMIT ${} OFFSET ${}",
+            where_clause,
+            sort.to_sql(),
+            sort.direction.to_sql(),
+            parameters.len() + 1,
+            parameters.len() + 2,
+        );
+        parameters.push(&pagination.limit);
+        parameters.push(&pagination.offset);
+        let rows = client.query(&query, &parameters).await?;
+        let entries: Vec<dto::Entry> = rows.iter().map(entry_from_row).collect();
+        Ok((entries, total))
+    }
+
+    /// Returns one page of the matching transactions, split ones included,
+    /// along with the total number of matches.
+    pub(super) async fn get_transactions(
+        &self,
+        filters: &filter::Filters<filter::EntryFields>,
+        sort: &filter::Sort<filter::EntryFields>,
+        pagination: &filter::Pagination,
+    ) -> Result<(Vec<dto::Transaction>, i64), RepositoryError> {
+        let filter = filters.build();
+        let where_clause = if filter.condition.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", filter.condition)
+        };
+        let mut parameters = filter.sql_parameters();
+
         let client = self.pool.get().await?;
-        let rows = client.query(&query, &[]).await?;
-        let entries: Vec<dto::Entry> = rows
+        let count_query = format!(
+            "SELECT count(DISTINCT id) FROM transaction_legs{}",
+            where_clause
+        );
+        let total: i64 = client.query_one(&count_query, &parameters).await?.get(0);
+
+        // A split entry matches once per leg, the subquery keeps one row per entry
+        let query = format!(
+            "SELECT id, description, event_date, reverses FROM (SELECT DISTINCT id, description, event_date, amount, reverses FROM transaction_legs{}) AS matching ORDER BY {}, id {} LIMIT ${} OFFSET ${}",
+            where_clause,
+            sort.to_sql(),
+            sort.direction.to_sql(),
+            parameters.len() + 1,
+            parameters.len() + 2,
+        );
+        parameters.push(&pagination.limit);
+        parameters.push(&pagination.offset);
+        let rows = client.query(&query, &parameters).await?;
+        let mut transactions: Vec<dto::Transaction> = rows
             .iter()
-            .map(|row| dto::Entry {
+            .map(|row| dto::Transaction {
                 id: row.get(0),
                 description: row.get(1),
-                amount: row.get(2),
-                event_date: row.get(3),
-                credit_id: row.get(4),
-                debit_id: row.get(5),
+                event_date: row.get(2),
+                reverses: row.get(3),
+                postings: Vec::new(),
             })
             .collect();
-        Ok(entries)
+
+        let ids: Vec<i32> = transactions.iter().map(|t| t.id).collect();
+        let postings_query =
+            "SELECT entry, account, amount FROM postings WHERE entry = ANY($1) ORDER BY id";
+        for row in client.query(postings_query, &[&ids]).await? {
+            let entry: i32 = row.get(0);
+            if let Some(transaction) = transactions.iter_mut().find(|t| t.id == entry) {
+                transaction.postings.push(dto::Posting {
+                    account_id: row.get(1),
+                    amount: row.get(2),
+                });
+            }
+        }
+        Ok((transactions, total))
+    }
+
+    /// Returns the number of deleted rows, 0 when no entry has this id.
+    /// Fails with `RepositoryError::InUse` when a reversal still points to the entry.
+    pub(super) async fn delete_entry(&self, id: i32) -> Result<u64, RepositoryError> {
+        let query = "DELETE FROM entries WHERE id = $1";
+        let client = self.pool.get().await?;
+        match client.execute(query, &[&id]).await {
+            Ok(deleted) => Ok(deleted),
+            Err(e) if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) => Err(
+                RepositoryError::InUse(format!("Entry {} is referenced by its reversal", id)),
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(super) async fn insert_recurrence(
+        &self,
+        recurrence: &dto::Recurrence,
+    ) -> Result<i32, RepositoryError> {
+        let query = "INSERT INTO recurrences (description, amount, credit, debit, start_date, end_date, frequence) VALUES ($1, $2, $3, $4, $5, $6, $7::text::frequence) RETURNING id";
+        let client = self.pool.get().await?;
+        let row = client
+            .query_one(
+                query,
+                &[
+                    &recurrence.description,
+                    &recurrence.amount,
+                    &recurrence.credit_id,
+                    &recurrence.debit_id,
+                    &recurrence.start_date,
+                    &recurrence.end_date,
+                    &recurrence.frequence,
+                ],
+            )
+            .await?;
+        Ok(row.get(0))
+    }
+
+    pub(super) async fn get_recurrence(
+        &self,
+        id: i32,
+    ) -> Result<Option<dto::Recurrence>, RepositoryError> {
+        let query = "SELECT id, description, amount, credit, debit, start_date, end_date, frequence::text FROM recurrences WHERE id = $1";
+        let client = self.pool.get().await?;
+        let row = client.query_opt(query, &[&id]).await?;
+        Ok(row.as_ref().map(recurrence_from_row))
+    }
+
+    pub(super) async fn get_recurrences(&self) -> Result<Vec<dto::Recurrence>, RepositoryError> {
+        let query = "SELECT id, description, amount, credit, debit, start_date, end_date, frequence::text FROM recurrences ORDER BY id";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[]).await?;
+        let recurrences: Vec<dto::Recurrence> = rows.iter().map(recurrence_from_row).collect();
+        Ok(recurrences)
+    }
+
+    /// Returns `None` when no recurrence has this id.
+    pub(super) async fn update_recurrence(
+        &self,
+        id: i32,
+        recurrence: &dto::Recurrence,
+    ) -> Result<Option<dto::Recurrence>, RepositoryError> {
+        let query = "UPDATE recurrences SET description = $2, amount = $3, credit = $4, debit = $5, start_date = $6, end_date = $7, frequence = $8::text::frequence WHERE id = $1 RETURNING id, description, amount, credit, debit, start_date, end_date, frequence::text";
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                query,
+                &[
+                    &id,
+                    &recurrence.description,
+                    &recurrence.amount,
+                    &recurrence.credit_id,
+                    &recurrence.debit_id,
+                    &recurrence.start_date,
+                    &recurrence.end_date,
+                    &recurrence.frequence,
+                ],
+            )
+            .await?;
+        Ok(row.as_ref().map(recurrence_from_row))
+    }
+
+    /// Returns the number of deleted rows, 0 when no recurrence has this id.
+    /// Entries already posted are kept, they only lose their link to the recurrence.
+    pub(super) async fn delete_recurrence(&self, id: i32) -> Result<u64, RepositoryError> {
+        let query = "DELETE FROM recurrences WHERE id = $1";
+        let client = self.pool.get().await?;
+        let deleted = client.execute(query, &[&id]).await?;
+        Ok(deleted)
+    }
+
+    /// Latest occurrence day already posted for the recurrence.
+    pub(super) async fn get_last_occurrence(
+        &self,
+        recurrence_id: i32,
+    ) -> Result<Option<NaiveDate>, RepositoryError> {
+        let query = "SELECT max(occurrence) FROM entries WHERE recurrence = $1";
+        let client = self.pool.get().await?;
+        let row = client.query_one(query, &[&recurrence_id]).await?;
+        Ok(row.get(0))
+    }
+
+    /// Posts the entry of one occurrence, does nothing when it was already posted.
+    /// Returns the number of created entries.
+    pub(super) async fn insert_occurrence(
+        &self,
+        recurrence_id: i32,
+        occurrence: NaiveDate,
+        transaction: &dto::Transaction,
+    ) -> Result<u64, RepositoryError> {
+        let query = "INSERT INTO entries (description, event_date, recurrence, occurrence) VALUES ($1, $2, $3, $4) ON CONFLICT (recurrence, occurrence) DO NOTHING RETURNING id";
+        let mut client = self.pool.get().await?;
+        let db_transaction = client.transaction().await?;
+        let row = db_transaction
+            .query_opt(
+                query,
+                &[
+                    &transaction.description,
+                    &transaction.event_date,
+                    &recurrence_id,
+                    &occurrence,
+                ],
+            )
+            .await?;
+        let Some(row) = row else {
+            return Ok(0);
+        };
+
+        insert_postings(&db_transaction, row.get(0), &transaction.postings).await?;
+        db_transaction.commit().await?;
+        Ok(1)
+    }
+
+    pub(super) async fn insert_budget(&self, budget: &dto::Budget) -> Result<i32, RepositoryError> {
+        let query = "INSERT INTO budgets (account, amount, frequence) VALUES ($1, $2, $3::text::frequence) RETURNING id";
+        let client = self.pool.get().await?;
+        let row = client
+            .query_one(
+                query,
+                &[&budget.account_id, &budget.amount, &budget.frequence],
+            )
+            .await?;
+        Ok(row.get(0))
+    }
+
+    pub(super) async fn get_budget(&self, id: i32) -> Result<Option<dto::Budget>, RepositoryError> {
+        let query = "SELECT id, account, amount, frequence::text FROM budgets WHERE id = $1";
+        let client = self.pool.get().await?;
+        let row = client.query_opt(query, &[&id]).await?;
+        Ok(row.as_ref().map(budget_from_row))
+    }
+
+    pub(super) async fn get_budgets(&self) -> Result<Vec<dto::Budget>, RepositoryError> {
+        let query = "SELECT id, account, amount, frequence::text FROM budgets ORDER BY id";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[]).await?;
+        let budgets: Vec<dto::Budget> = rows.iter().map(budget_from_row).collect();
+        Ok(budgets)
+    }
+
+    /// Returns `None` when no budget has this id.
+    pub(super) async fn update_budget(
+        &self,
+        id: i32,
+        budget: &dto::Budget,
+    ) -> Result<Option<dto::Budget>, RepositoryError> {
+        let query = "UPDATE budgets SET account = $2, amount = $3, frequence = $4::text::frequence WHERE id = $1 RETURNING id, account, amount, frequence::text";
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                query,
+                &[&id, &budget.account_id, &budget.amount, &budget.frequence],
+            )
+            .await?;
+        Ok(row.as_ref().map(budget_from_row))
+    }
+
+    /// Returns the number of deleted rows, 0 when no budget has this id.
+    pub(super) async fn delete_budget(&self, id: i32) -> Result<u64, RepositoryError> {
+        let query = "DELETE FROM budgets WHERE id = $1";
+        let client = self.pool.get().await?;
+        let deleted = client.execute(query, &[&id]).await?;
+        Ok(deleted)
+    }
+
+    pub(super) async fn insert_envelope_transfer(
+        &self,
+        transfer: &dto::EnvelopeTransfer,
+    ) -> Result<i32, RepositoryError> {
+        let query = "INSERT INTO envelope_transfers (month, source, target, amount) VALUES ($1, $2, $3, $4) RETURNING id";
+        let client = self.pool.get().await?;
+        let row = client
+            .query_one(
+                query,
+                &[
+                    &transfer.month,
+                    &transfer.source,
+                    &transfer.target,
+                    &transfer.amount,
+                ],
+            )
+            .await?;
+        Ok(row.get(0))
+    }
+
+    /// Writes all the transfers at once, none of them when one fails.
+    pub(super) async fn insert_envelope_transfers(
+        &self,
+        transfers: &[dto::EnvelopeTransfer],
+    ) -> Result<(), RepositoryError> {
+        let query = "INSERT INTO envelope_transfers (month, source, target, amount) VALUES ($1, $2, $3, $4)";
+        let mut client = self.pool.get().await?;
+        let db_transaction = client.transaction().await?;
+        for transfer in transfers {
+            db_transaction
+                .execute(
+                    query,
+                    &[
+                        &transfer.month,
+                        &transfer.source,
+                        &transfer.target,
+                        &transfer.amount,
+                    ],
+                )
+                .await?;
+        }
+        db_transaction.commit().await?;
+        Ok(())
+    }
+
+    pub(super) async fn get_envelope_transfer(
+        &self,
+        id: i32,
+    ) -> Result<Option<dto::EnvelopeTransfer>, RepositoryError> {
+        let query =
+            "SELECT id, month, source, target, amount FROM envelope_transfers WHERE id = $1";
+        let client = self.pool.get().await?;
+        let row = client.query_opt(query, &[&id]).await?;
+        Ok(row.as_ref().map(envelope_transfer_from_row))
+    }
+
+    /// Transfers of the month starting on `month`.
+    pub(super) async fn get_month_envelope_transfers(
+        &self,
+        month: NaiveDate,
+    ) -> Result<Vec<dto::EnvelopeTransfer>, RepositoryError> {
+        let query = "SELECT id, month, source, target, amount FROM envelope_transfers WHERE month = $1 ORDER BY id";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[&month]).await?;
+        let transfers: Vec<dto::EnvelopeTransfer> =
+            rows.iter().map(envelope_transfer_from_row).collect();
+        Ok(transfers)
+    }
+
+    /// Transfers of the months up to `until`, included, oldest first.
+    pub(super) async fn get_envelope_transfers(
+        &self,
+        until: NaiveDate,
+    ) -> Result<Vec<dto::EnvelopeTransfer>, RepositoryError> {
+        let query = "SELECT id, month, source, target, amount FROM envelope_transfers WHERE month <= $1 ORDER BY month, id";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[&until]).await?;
+        let transfers: Vec<dto::EnvelopeTransfer> =
+            rows.iter().map(envelope_transfer_from_row).collect();
+        Ok(transfers)
+    }
+
+    /// Accounts used as envelopes by at least one transfer.
+    pub(super) async fn get_envelope_account_ids(&self) -> Result<Vec<i32>, RepositoryError> {
+        let query = "SELECT source FROM envelope_transfers WHERE source IS NOT NULL \
+            UNION SELECT target FROM envelope_transfers WHERE target IS NOT NULL";
+        let client = self.pool.get().await?;
+        let rows = client.query(query, &[]).await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// Returns the number of deleted rows, 0 when no transfer has this id.
+    pub(super) async fn delete_envelope_transfer(&self, id: i32) -> Result<u64, RepositoryError> {
+        let query = "DELETE FROM envelope_transfers WHERE id = $1";
+        let client = self.pool.get().await?;
+        let deleted = client.execute(query, &[&id]).await?;
+        Ok(deleted)
+    }
+
+    /// Sum of the postings of the account and of all its sub-accounts, over the
+    /// entries matching the filters.
+    pub(super) async fn get_filtered_account_subtotal(
+        &self,
+        id: i32,
+        filters: &filter::Filters<filter::EntryFields>,
+    ) -> Result<Money, RepositoryError> {
+        let filter = filters.build();
+        let mut parameters = filter.sql_parameters();
+        let query = format!(
+            "WITH RECURSIVE subtree(id) AS ( \
+            SELECT id FROM accounts WHERE id = ${} \
+            UNION ALL \
+            SELECT accounts.id FROM accounts JOIN subtree ON accounts.parent = subtree.id \
+        ) \
+        SELECT COALESCE(sum(movements.amount), 0.0) \
+        FROM ({}) AS movements JOIN subtree ON subtree.id = movements.account_id",
+            parameters.len() + 1,
+            movements_query(&filter)
+        );
+        parameters.push(&id);
+        let client = self.pool.get().await?;
+        let row = client.query_one(&query, &parameters).await?;
+        Ok(row.get(0))
+    }
+}
+
+async fn insert_postings(
+    db_transaction: &tokio_postgres::Transaction<'_>,
+    entry_id: i32,
+    postings: &[dto::Posting],
+) -> Result<(), RepositoryError> {
+    let query = "INSERT INTO postings (entry, account, amount) VALUES ($1, $2, $3)";
+    let statement = db_transaction.prepare(query).await?;
+    for posting in postings {
+        db_transaction
+            .execute(
+                &statement,
+                &[&entry_id, &posting.account_id, &posting.amount],
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Postings of the entries matching the filter, as `account_id` and `amount` columns.
+fn movements_query(filter: &filter::SqlFilter) -> String {
+    let condition = if filter.condition.is_empty() {
+        "TRUE"
+    } else {
+        &filter.condition
+    };
+    format!(
+        "SELECT account_ledgers.account_id, account_ledgers.amount \
+        FROM account_ledgers JOIN entries ON entries.id = account_ledgers.entry_id \
+        WHERE {}",
+        condition
+    )
+}
+
+fn violated_constraint(e: &tokio_postgres::Error) -> Option<&str> {
+    e.as_db_error().and_then(|db_error| db_error.constraint())
+}
+
+fn account_from_row(row: &Row) -> dto::Account {
+    dto::Account {
+        id: row.get(0),
+        name: row.get(1),
+        family: row.get(2),
+        parent: row.get(3),
+    }
+}
+
+fn entry_from_row(row: &Row) -> dto::Entry {
+    dto::Entry {
+        id: row.get(0),
+        description: row.get(1),
+        amount: row.get(2),
+        event_date: row.get(3),
+        credit_id: row.get(4),
+        debit_id: row.get(5),
+        reverses: row.get(6),
+    }
+}
+
+fn envelope_transfer_from_row(row: &Row) -> dto::EnvelopeTransfer {
+    dto::EnvelopeTransfer {
+        id: row.get(0),
+        month: row.get(1),
+        source: row.get(2),
+        target: row.get(3),
+        amount: row.get(4),
+    }
+}
+
+fn budget_from_row(row: &Row) -> dto::Budget {
+    dto::Budget {
+        id: row.get(0),
+        account_id: row.get(1),
+        amount: row.get(2),
+        frequence: row.get(3),
+    }
+}
+
+fn recurrence_from_row(row: &Row) -> dto::Recurrence {
+    dto::Recurrence {
+        id: row.get(0),
+        description: row.get(1),
+        amount: row.get(2),
+        credit_id: row.get(3),
+        debit_id: row.get(4),
+        start_date: row.get(5),
+        end_date: row.get(6),
+        frequence: row.get(7),
     }
 }
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
 use std::time::Duration;
 
-use futures::{StreamExt, stream};
+use futures::{Future, StreamExt, stream};
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc;
 use tokio_postgres::AsyncMessage;
 use tokio_postgres::{
     Config, Socket,
@@ -8,8 +13,87 @@ use tokio_postgres::{
 };
 use tracing::{Level, instrument};
 
-pub trait NotificationHandler {
-    fn on_notification_received(&self, channel: &str, message: &str);
+/// Delay between two heartbeat queries keeping the connection alive.
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(10);
+
+/// First delay before reconnecting, doubled after every failed attempt.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between two reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Handles the notifications of one channel, one at a time and in the order Postgres sent them.
+pub trait NotificationHandler: Send + Sync + 'static {
+    /// JSON payload of the channel.
+    type Event: DeserializeOwned + Debug + Send;
+
+    fn on_notification_received(&self, event: Self::Event) -> impl Future<Output = ()> + Send;
+
+    /// Called once listening again after a connection loss.
+    /// Notifications sent in between are lost, so any derived state must be rebuilt.
+    fn on_resync_required(&self) -> impl Future<Output = ()> + Send;
+}
+
+/// Queued for the handler of a channel.
+enum ChannelMessage {
+    Notification(String),
+    Resync,
+}
+
+type ChannelQueues = HashMap<String, mpsc::UnboundedSender<ChannelMessage>>;
+
+/// Feeds the queued messages of `channel` to its handler, so a slow handler never
+/// blocks the connection.
+async fn dispatch<H>(
+    channel: String,
+    handler: H,
+    mut received: mpsc::UnboundedReceiver<ChannelMessage>,
+) where
+    H: NotificationHandler,
+{
+    while let Some(message) = received.recv().await {
+        match message {
+            ChannelMessage::Notification(payload) => {
+                match serde_json::from_str::<H::Event>(&payload) {
+                    Ok(event) => {
+                        tracing::event!(Level::DEBUG, "{} notification: {:?}", channel, event);
+                        handler.on_notification_received(event).await;
+                    }
+                    Err(e) => tracing::event!(
+                        Level::WARN,
+                        "Ignoring malformed notification on {}: {}",
+                        channel,
+                        e
+                    ),
+                }
+            }
+            ChannelMessage::Resync => handler.on_resync_required().await,
+        }
+    }
+}
+
+/// Exponential delay between reconnection attempts.
+struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self {
+            delay: MIN_RECONNECT_DELAY,
+        }
+    }
+
+    /// Delay to wait now, the following one is doubled up to `MAX_RECONNECT_DELAY`.
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(MAX_RECONNECT_DELAY);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.delay = MIN_RECONNECT_DELAY;
+    }
 }
 
 pub struct DatabaseListener<T>
@@ -21,6 +105,7 @@ where
 {
     pg_config: Config,
     tls: T,
+    channels: ChannelQueues,
 }
 
 impl<T> DatabaseListener<T>
@@ -31,50 +116,139 @@ where
     <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
 {
     pub fn new(pg_config: Config, tls: T) -> Self {
-        Self { pg_config, tls }
+        Self {
+            pg_config,
+            tls,
+            channels: HashMap::new(),
+        }
     }
 
-    #[instrument(name = "db_listener", level = Level::DEBUG, skip(handler, channel, self))]
-    pub async fn attach<H>(self, handler: H, channel: &str)
+    /// Routes the notifications of `channel` to `handler`, run in its own task.
+    pub fn subscribe<H>(mut self, channel: &str, handler: H) -> Self
     where
-        H: NotificationHandler + Sync + Send + 'static,
+        H: NotificationHandler,
     {
-        let (client, mut connection) = self.pg_config.connect(self.tls).await.unwrap();
+        let (queue, received) = mpsc::unbounded_channel();
+        tokio::spawn(dispatch(channel.to_string(), handler, received));
+        self.channels.insert(channel.to_string(), queue);
+        self
+    }
+
+    /// Listens to the subscribed channels forever, reconnecting with an exponential
+    /// backoff whenever the connection is lost.
+    #[instrument(name = "db_listener", level = Level::DEBUG, skip(self))]
+    pub async fn listen(self) {
+        let channels = Arc::new(self.channels);
+        let mut backoff = Backoff::new();
+        let mut reconnecting = false;
+
+        loop {
+            let res = Self::run_connection(
+                &self.pg_config,
+                self.tls.clone(),
+                &channels,
+                reconnecting,
+                &mut backoff,
+            )
+            .await;
+            if let Err(e) = res {
+                tracing::event!(Level::ERROR, "Listener connection lost: {}", e);
+            }
+
+            reconnecting = true;
+            let delay = backoff.next_delay();
+            tracing::event!(Level::INFO, "Reconnecting listener in {:?}", delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Runs one connection until it fails.
+    async fn run_connection(
+        pg_config: &Config,
+        tls: T,
+        channels: &Arc<ChannelQueues>,
+        reconnecting: bool,
+        backoff: &mut Backoff,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (client, mut connection) = pg_config.connect(tls).await?;
 
         // Notification listen
         let notif_span = tracing::span!(Level::TRACE, "receiver");
-        tokio::spawn(async move {
+        let queues = channels.clone();
+        let mut receiver = tokio::spawn(async move {
             let _guard = notif_span.enter();
 
             let mut stream = stream::poll_fn(|cx| connection.poll_message(cx));
-            while let Some(Ok(message)) = stream.next().await {
-                if let AsyncMessage::Notification(notification) = message {
-                    handler
-                        .on_notification_received(notification.channel(), notification.payload());
+            while let Some(message) = stream.next().await {
+                if let AsyncMessage::Notification(notification) = message? {
                     tracing::event!(Level::TRACE, "received: {:?}", notification);
+                    if let Some(queue) = queues.get(notification.channel()) {
+                        let payload = notification.payload().to_string();
+                        let _ = queue.send(ChannelMessage::Notification(payload));
+                    }
                 }
             }
 
             drop(_guard);
+            Ok::<(), tokio_postgres::Error>(())
         });
 
-        let listen_query = format!("LISTEN {}", channel);
-        client.simple_query(&listen_query).await.unwrap();
+        let subscribed = Self::subscribe_all(&client, channels).await;
+        if let Err(e) = subscribed {
+            receiver.abort();
+            return Err(e.into());
+        }
+        backoff.reset();
+        tracing::event!(Level::INFO, "Listening to {:?}", channels.keys());
 
-        // Heartbeat query to keep the connection alive
-        let hearbeat_span = tracing::span!(Level::TRACE, "heartbeat");
-        tokio::spawn(async move {
-            let _guard = hearbeat_span.enter();
-
-            loop {
-                client.simple_query("SELECT 1").await.unwrap();
+        if reconnecting {
+            for queue in channels.values() {
+                let _ = queue.send(ChannelMessage::Resync);
+            }
+        }
 
-                tracing::event!(Level::TRACE, "Heart beat 10 seconds");
-                tokio::time::sleep(Duration::from_secs(10)).await;
+        // Heartbeat query to keep the connection alive, and notice when it is lost
+        loop {
+            tokio::select! {
+                res = &mut receiver => {
+                    res??;
+                    return Err("connection closed".into());
+                }
+                _ = tokio::time::sleep(HEARTBEAT_PERIOD) => {
+                    let heartbeat = client.simple_query("SELECT 1").await;
+                    if let Err(e) = heartbeat {
+                        receiver.abort();
+                        return Err(e.into());
+                    }
+                    tracing::event!(Level::TRACE, "Heart beat {:?}", HEARTBEAT_PERIOD);
+                }
             }
+        }
+    }
 
-            #[allow(unreachable_code)]
-            drop(_guard);
-        });
+    async fn subscribe_all(
+        client: &tokio_postgres::Client,
+        channels: &ChannelQueues,
+    ) -> Result<(), tokio_postgres::Error> {
+        for channel in channels.keys() {
+            let listen_query = format!("LISTEN \"{}\"", channel);
+            client.simple_query(&listen_query).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max_and_resets() {
+        let mut backoff = Backoff::new();
+        let delays: Vec<u64> = (0..8).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), MIN_RECONNECT_DELAY);
     }
 }
@@ -1,24 +1,159 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
 
-use crate::model;
+use crate::model::{self, money::Money};
 
 #[derive(Debug)]
 pub struct Account {
     pub id: i32,
     pub name: String,
     pub family: i32,
+    pub parent: Option<i32>,
 }
 
 #[derive(Debug)]
 pub struct Entry {
-    #[allow(dead_code)]
     pub id: i32,
+    pub description: String,
+    pub amount: Money,
+    pub event_date: DateTime<Utc>,
+    pub credit_id: i32,
+    pub debit_id: i32,
+    pub reverses: Option<i32>,
+}
 
+#[derive(Debug)]
+pub struct Posting {
+    pub account_id: i32,
+    pub amount: Money,
+}
+
+#[derive(Debug)]
+pub struct Transaction {
+    pub id: i32,
     pub description: String,
-    pub amount: f64,
     pub event_date: DateTime<Utc>,
+    pub reverses: Option<i32>,
+    pub postings: Vec<Posting>,
+}
+
+#[derive(Debug)]
+pub struct AccountBalance {
+    pub id: i32,
+    pub balance: Money,
+}
+
+#[derive(Debug)]
+pub struct TrialBalanceLine {
+    pub account_id: i32,
+    pub debits: Money,
+    pub credits: Money,
+}
+
+#[derive(Debug)]
+pub struct CashFlow {
+    pub account_id: i32,
+    pub inflow: Money,
+    pub outflow: Money,
+}
+
+#[derive(Debug)]
+pub struct FamilyMovement {
+    /// Index of the period, see `Dao::get_family_movements`.
+    pub period: i32,
+    pub family: i32,
+    pub amount: Money,
+}
+
+impl FamilyMovement {
+    pub fn to_model(&self) -> model::report::FamilyMovement {
+        model::report::FamilyMovement {
+            period: self.period as usize,
+            family: family_from_int(self.family),
+            amount: self.amount,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BalanceMismatch {
+    pub account_id: i32,
+    /// None when the account is missing from `account_balances`.
+    pub recorded: Option<Money>,
+    pub computed: Money,
+}
+
+#[derive(Debug)]
+pub struct LedgerLine {
+    pub entry_id: i32,
+    pub description: String,
+    pub event_date: DateTime<Utc>,
+    pub amount: Money,
+    pub counter_account_id: i32,
+}
+
+#[derive(Debug)]
+pub struct Recurrence {
+    pub id: i32,
+    pub description: String,
+    pub amount: Money,
     pub credit_id: i32,
     pub debit_id: i32,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub frequence: String,
+}
+
+#[derive(Debug)]
+pub struct Budget {
+    pub id: i32,
+    pub account_id: i32,
+    pub amount: Money,
+    pub frequence: String,
+}
+
+#[derive(Debug)]
+pub struct EnvelopeTransfer {
+    pub id: i32,
+    pub month: NaiveDate,
+    pub source: Option<i32>,
+    pub target: Option<i32>,
+    pub amount: Money,
+}
+
+/// Kind of change notified by the row triggers, see `TG_OP`.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Operation {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Payload of the `accounts` channel, sent by `notify_accounts()`.
+#[derive(Debug, Deserialize)]
+pub struct AccountChange {
+    pub operation: Operation,
+    pub id: i32,
+    pub name: String,
+    pub family: i32,
+    pub parent: Option<i32>,
+}
+
+/// Payload of the `entries` channel, sent by `notify_entries()`.
+#[derive(Debug, Deserialize)]
+pub struct EntryChange {
+    pub operation: Operation,
+    pub id: i32,
+    /// Accounts posted to before an update or a deletion, empty on inserts.
+    pub accounts: Vec<i32>,
+}
+
+/// Payload of the `recurrences` channel, sent by `notify_recurrences()`.
+#[derive(Debug, Deserialize)]
+pub struct RecurrenceChange {
+    pub operation: Operation,
+    pub id: i32,
 }
 
 pub trait DtoModelNoRef<T> {
@@ -32,13 +167,17 @@ impl DtoModelNoRef<model::account::Account> for Account {
             id: -1,
             name: t.name.clone(),
             family: family_to_int(&t.family),
+            parent: t.parent_id,
         }
     }
 
     fn to_model(&self) -> model::account::Account {
         model::account::Account {
+            id: Some(self.id),
             name: self.name.clone(),
             family: family_from_int(self.family),
+            parent_id: self.parent,
+            path: None, // Set by the account cache, from the ancestors
         }
     }
 }
@@ -49,29 +188,191 @@ impl DtoModelNoRef<model::entry::Entry> for Entry {
             id: -1,
             description: t.description.clone(),
             amount: t.amount,
-            event_date: t.event_date.clone(),
+            event_date: t.event_date,
             credit_id: -1,
             debit_id: -1,
+            reverses: t.reverses,
         }
     }
 
     fn to_model(&self) -> model::entry::Entry {
         model::entry::Entry {
+            id: Some(self.id),
             description: self.description.clone(),
             amount: self.amount,
-            event_date: self.event_date.clone(),
+            event_date: self.event_date,
             credit: model::account::Account {
+                id: None,
                 name: String::new(), // Placeholder, should fetch account details
                 family: model::account::AccountFamily::Asset, // Placeholder, should fetch account details
+                parent_id: None,
+                path: None,
             },
             debit: model::account::Account {
+                id: None,
                 name: String::new(), // Placeholder, should fetch account details
                 family: model::account::AccountFamily::Asset, // Placeholder, should fetch account details
+                parent_id: None,
+                path: None,
             },
+            reverses: self.reverses,
         }
     }
 }
 
+impl DtoModelNoRef<model::transaction::Transaction> for Transaction {
+    fn from_model(t: &model::transaction::Transaction) -> Self {
+        Self {
+            id: -1,
+            description: t.description.clone(),
+            event_date: t.event_date,
+            reverses: t.reverses,
+            postings: t
+                .postings
+                .iter()
+                .map(|posting| Posting {
+                    account_id: -1,
+                    amount: posting.amount,
+                })
+                .collect(),
+        }
+    }
+
+    fn to_model(&self) -> model::transaction::Transaction {
+        model::transaction::Transaction {
+            id: Some(self.id),
+            description: self.description.clone(),
+            event_date: self.event_date,
+            postings: self
+                .postings
+                .iter()
+                .map(|posting| model::transaction::Posting {
+                    account: model::account::Account {
+                        id: None,
+                        name: String::new(), // Placeholder, should fetch account details
+                        family: model::account::AccountFamily::Asset, // Placeholder, should fetch account details
+                        parent_id: None,
+                        path: None,
+                    },
+                    amount: posting.amount,
+                })
+                .collect(),
+            reverses: self.reverses,
+        }
+    }
+}
+
+impl DtoModelNoRef<model::recurrence::Recurrence> for Recurrence {
+    fn from_model(t: &model::recurrence::Recurrence) -> Self {
+        Self {
+            id: -1,
+            description: t.description.clone(),
+            amount: t.amount,
+            credit_id: -1,
+            debit_id: -1,
+            start_date: t.start_date,
+            end_date: t.end_date,
+            frequence: frequence_to_str(&t.frequence).to_string(),
+        }
+    }
+
+    fn to_model(&self) -> model::recurrence::Recurrence {
+        model::recurrence::Recurrence {
+            id: Some(self.id),
+            description: self.description.clone(),
+            amount: self.amount,
+            credit: model::account::Account {
+                id: None,
+                name: String::new(), // Placeholder, should fetch account details
+                family: model::account::AccountFamily::Asset, // Placeholder, should fetch account details
+                parent_id: None,
+                path: None,
+            },
+            debit: model::account::Account {
+                id: None,
+                name: String::new(), // Placeholder, should fetch account details
+                family: model::account::AccountFamily::Asset, // Placeholder, should fetch account details
+                parent_id: None,
+                path: None,
+            },
+            start_date: self.start_date,
+            end_date: self.end_date,
+            frequence: frequence_from_str(&self.frequence),
+        }
+    }
+}
+
+impl DtoModelNoRef<model::budget::Budget> for Budget {
+    fn from_model(t: &model::budget::Budget) -> Self {
+        Self {
+            id: -1,
+            account_id: -1,
+            amount: t.amount,
+            frequence: frequence_to_str(&t.frequence).to_string(),
+        }
+    }
+
+    fn to_model(&self) -> model::budget::Budget {
+        model::budget::Budget {
+            id: Some(self.id),
+            account: model::account::Account {
+                id: None,
+                name: String::new(), // Placeholder, should fetch account details
+                family: model::account::AccountFamily::Asset, // Placeholder, should fetch account details
+                parent_id: None,
+                path: None,
+            },
+            amount: self.amount,
+            frequence: frequence_from_str(&self.frequence),
+        }
+    }
+}
+
+impl DtoModelNoRef<model::envelope::EnvelopeTransfer> for EnvelopeTransfer {
+    fn from_model(t: &model::envelope::EnvelopeTransfer) -> Self {
+        Self {
+            id: -1,
+            month: t.month,
+            source: None,
+            target: None,
+            amount: t.amount,
+        }
+    }
+
+    fn to_model(&self) -> model::envelope::EnvelopeTransfer {
+        model::envelope::EnvelopeTransfer {
+            id: Some(self.id),
+            month: self.month,
+            from: None, // Placeholder, should fetch account details
+            to: None,   // Placeholder, should fetch account details
+            amount: self.amount,
+        }
+    }
+}
+
+/// Converts a FREQUENCE label to a model::recurrence::Frequence enum.
+/// Postgres stores frequences as an enum, read as text, so this function maps them accordingly.
+fn frequence_from_str(frequence: &str) -> model::recurrence::Frequence {
+    match frequence {
+        "daily" => model::recurrence::Frequence::Daily,
+        "weekly" => model::recurrence::Frequence::Weekly,
+        "monthly" => model::recurrence::Frequence::Monthly,
+        "yearly" => model::recurrence::Frequence::Yearly,
+        _ => panic!("Invalid frequence label"),
+    }
+}
+
+/// Converts a model::recurrence::Frequence enum to a FREQUENCE label.
+/// Postgres stores frequences as an enum, written as text, so this function maps them accordingly.
+fn frequence_to_str(frequence: &model::recurrence::Frequence) -> &'static str {
+    match frequence {
+        model::recurrence::Frequence::Daily => "daily",
+        model::recurrence::Frequence::Weekly => "weekly",
+        model::recurrence::Frequence::Monthly => "monthly",
+        model::recurrence::Frequence::Yearly => "yearly",
+    }
+}
+
 /// Converts an integer to a model::account::AccountFamily enum.
 /// Postgres stores account families as integers, so this function maps them accordingly.
 fn family_from_int(family: i32) -> model::account::AccountFamily {
@@ -87,7 +388,7 @@ fn family_from_int(family: i32) -> model::account::AccountFamily {
 
 /// Converts a model::account::AccountFamily enum to an integer.
 /// Postgres stores account families as integers, so this function maps them accordingly.
-fn family_to_int(family: &model::account::AccountFamily) -> i32 {
+pub(super) fn family_to_int(family: &model::account::AccountFamily) -> i32 {
     match family {
         model::account::AccountFamily::Asset => 1,
         model::account::AccountFamily::Liability => 2,
@@ -96,3 +397,52 @@ fn family_to_int(family: &model::account::AccountFamily) -> i32 {
         model::account::AccountFamily::Expense => 5,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Payloads as printed by `json_build_object(...)::text` in the notify_* functions
+
+    #[test]
+    fn test_account_change_payloads() {
+        let payload = r#"{"operation" : "INSERT", "id" : 9, "name" : "Salary", "family" : 4, "parent" : null}"#;
+        let change: AccountChange = serde_json::from_str(payload).unwrap();
+        assert_eq!(change.operation, Operation::Insert);
+        assert_eq!(change.id, 9);
+        assert_eq!(change.name, "Salary");
+        assert_eq!(family_from_int(change.family), model::account::AccountFamily::Revenue);
+        assert_eq!(change.parent, None);
+
+        let payload = r#"{"operation" : "UPDATE", "id" : 7, "name" : "Internet", "family" : 5, "parent" : 6}"#;
+        let change: AccountChange = serde_json::from_str(payload).unwrap();
+        assert_eq!(change.operation, Operation::Update);
+        assert_eq!(change.parent, Some(6));
+    }
+
+    #[test]
+    fn test_entry_change_payloads() {
+        let payload = r#"{"operation" : "INSERT", "id" : 12, "accounts" : []}"#;
+        let change: EntryChange = serde_json::from_str(payload).unwrap();
+        assert_eq!(change.operation, Operation::Insert);
+        assert_eq!(change.id, 12);
+        assert!(change.accounts.is_empty());
+
+        let payload = r#"{"operation" : "DELETE", "id" : 9, "accounts" : [1, 4, 5]}"#;
+        let change: EntryChange = serde_json::from_str(payload).unwrap();
+        assert_eq!(change.operation, Operation::Delete);
+        assert_eq!(change.accounts, vec![1, 4, 5]);
+    }
+
+    #[test]
+    fn test_recurrence_change_payloads() {
+        let payload = r#"{"operation" : "DELETE", "id" : 1}"#;
+        let change: RecurrenceChange = serde_json::from_str(payload).unwrap();
+        assert_eq!(change.operation, Operation::Delete);
+        assert_eq!(change.id, 1);
+
+        // TG_OP is uppercase, other spellings are not notified
+        let payload = r#"{"operation" : "update", "id" : 1}"#;
+        assert!(serde_json::from_str::<RecurrenceChange>(payload).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use tokio_postgres::error::SqlState;
+
+use crate::model::problem::FieldError;
+
+/// Failure of a repository operation, each kind maps to its own HTTP status.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepositoryError {
+    /// No resource has this id.
+    NotFound(String),
+    /// The input is rejected, e.g. an unknown account name or a value out of range.
+    Validation(String),
+    /// Some input fields break the validation rules, see `validation::ValidationRules`.
+    InvalidFields(Vec<FieldError>),
+    /// A unique constraint is violated, or the operation was already done.
+    Conflict(String),
+    /// Other rows still reference the resource, which restricts its deletion.
+    InUse(String),
+    /// The database cannot be reached, or no connection is available.
+    Unavailable(String),
+    /// Anything unexpected, details are only meant for the logs.
+    Internal(String),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::NotFound(message)
+            | RepositoryError::Validation(message)
+            | RepositoryError::Conflict(message)
+            | RepositoryError::InUse(message)
+            | RepositoryError::Unavailable(message)
+            | RepositoryError::Internal(message) => write!(f, "{}", message),
+            RepositoryError::InvalidFields(errors) => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|error| format!("{}: {}", error.field, error.message))
+                    .collect();
+                write!(f, "{}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<tokio_postgres::Error> for RepositoryError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        let Some(db_error) = e.as_db_error() else {
+            // No answer from the server: I/O failure or closed connection
+            let io_failure =
+                std::error::Error::source(&e).is_some_and(|source| source.is::<std::io::Error>());
+            if e.is_closed() || io_failure {
+                return RepositoryError::Unavailable(e.to_string());
+            }
+            return RepositoryError::Internal(e.to_string());
+        };
+
+        let message = match db_error.detail() {
+            Some(detail) => format!("{}: {}", db_error.message(), detail),
+            None => db_error.message().to_string(),
+        };
+        let code = db_error.code();
+        match *code {
+            SqlState::UNIQUE_VIOLATION | SqlState::EXCLUSION_VIOLATION => {
+                RepositoryError::Conflict(message)
+            }
+            SqlState::FOREIGN_KEY_VIOLATION | SqlState::RESTRICT_VIOLATION => {
+                RepositoryError::InUse(message)
+            }
+            SqlState::NOT_NULL_VIOLATION
+            | SqlState::CHECK_VIOLATION
+            | SqlState::NUMERIC_VALUE_OUT_OF_RANGE
+            | SqlState::STRING_DATA_RIGHT_TRUNCATION
+            | SqlState::INVALID_TEXT_REPRESENTATION
+            | SqlState::DATETIME_FIELD_OVERFLOW => RepositoryError::Validation(message),
+            _ if is_unavailable(code) => RepositoryError::Unavailable(message),
+            _ => RepositoryError::Internal(message),
+        }
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for RepositoryError {
+    fn from(e: deadpool_postgres::PoolError) -> Self {
+        match e {
+            deadpool_postgres::PoolError::Backend(e) => e.into(),
+            e => RepositoryError::Unavailable(e.to_string()),
+        }
+    }
+}
+
+/// Connection exceptions (class 08), server shutting down or not accepting
+/// connections (57P0x) and exhausted resources (class 53).
+fn is_unavailable(code: &SqlState) -> bool {
+    let code = code.code();
+    code.starts_with("08") || code.starts_with("57P0") || code.starts_with("53")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unavailable_sql_states() {
+        assert!(is_unavailable(&SqlState::CONNECTION_FAILURE));
+        assert!(is_unavailable(&SqlState::ADMIN_SHUTDOWN));
+        assert!(is_unavailable(&SqlState::CANNOT_CONNECT_NOW));
+        assert!(is_unavailable(&SqlState::TOO_MANY_CONNECTIONS));
+        assert!(!is_unavailable(&SqlState::UNIQUE_VIOLATION));
+        assert!(!is_unavailable(&SqlState::QUERY_CANCELED));
+    }
+}
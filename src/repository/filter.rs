@@ -1,14 +1,37 @@
 use std::marker::PhantomData;
 
+use chrono::{DateTime, Utc};
+
+use crate::model::{account::AccountFamily, money::Money};
+use crate::repository::dto;
+
 pub enum EntryFields {
+    Id,
     EventDate,
+    Amount,
+    Description,
+    CreditAccount,
+    DebitAccount,
+    CreditFamily,
+    DebitFamily,
 }
 
 impl Fields for EntryFields {}
 impl ToSql for EntryFields {
     fn to_sql(&self) -> String {
         match self {
+            EntryFields::Id => "id".to_string(),
             EntryFields::EventDate => "event_date".to_string(),
+            EntryFields::Amount => "amount".to_string(),
+            EntryFields::Description => "description".to_string(),
+            EntryFields::CreditAccount => "credit".to_string(),
+            EntryFields::DebitAccount => "debit".to_string(),
+            EntryFields::CreditFamily => {
+                "(SELECT accounts.family FROM accounts WHERE accounts.id = credit)".to_string()
+            }
+            EntryFields::DebitFamily => {
+                "(SELECT accounts.family FROM accounts WHERE accounts.id = debit)".to_string()
+            }
         }
     }
 }
@@ -28,6 +51,8 @@ pub enum Operator {
     GreaterThanOrEqual,
     LessThan,
     LessThanOrEqual,
+    /// Case-insensitive `LIKE`, see `contains_pattern`.
+    ILike,
 }
 
 impl ToSql for Operator {
@@ -39,20 +64,170 @@ impl ToSql for Operator {
             Operator::GreaterThanOrEqual => ">=".to_string(),
             Operator::LessThan => "<".to_string(),
             Operator::LessThanOrEqual => "<=".to_string(),
+            Operator::ILike => "ILIKE".to_string(),
         }
     }
 }
 
-#[allow(dead_code)]
+/// `ILIKE` pattern matching any text containing `value`, wildcards in `value` included.
+pub fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
 enum BinaryOperator {
     And,
     Or,
 }
 
+impl ToSql for BinaryOperator {
+    fn to_sql(&self) -> String {
+        match self {
+            BinaryOperator::And => "AND".to_string(),
+            BinaryOperator::Or => "OR".to_string(),
+        }
+    }
+}
+
+/// Value compared to a field, always sent to Postgres as a bind parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Date(DateTime<Utc>),
+    Integer(i32),
+    Decimal(Money),
+    Text(String),
+}
+
+impl FilterValue {
+    fn as_sql_parameter(&self) -> &(dyn postgres_types::ToSql + Sync) {
+        match self {
+            FilterValue::Date(value) => value,
+            FilterValue::Integer(value) => value,
+            FilterValue::Decimal(value) => value,
+            FilterValue::Text(value) => value,
+        }
+    }
+}
+
+impl From<DateTime<Utc>> for FilterValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        FilterValue::Date(value)
+    }
+}
+
+impl From<i32> for FilterValue {
+    fn from(value: i32) -> Self {
+        FilterValue::Integer(value)
+    }
+}
+
+impl From<Money> for FilterValue {
+    fn from(value: Money) -> Self {
+        FilterValue::Decimal(value)
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(value: String) -> Self {
+        FilterValue::Text(value)
+    }
+}
+
+impl From<&AccountFamily> for FilterValue {
+    fn from(value: &AccountFamily) -> Self {
+        FilterValue::Integer(dto::family_to_int(value))
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(value: &str) -> Self {
+        FilterValue::Text(value.to_string())
+    }
+}
+
+/// Output of the filter builder: a SQL condition with `$1`, `$2`, ... placeholders
+/// and the values to bind to them, in order.
+#[derive(Debug)]
+pub struct SqlFilter {
+    pub condition: String,
+    pub parameters: Vec<FilterValue>,
+}
+
+impl SqlFilter {
+    pub(super) fn sql_parameters(&self) -> Vec<&(dyn postgres_types::ToSql + Sync)> {
+        self.parameters
+            .iter()
+            .map(FilterValue::as_sql_parameter)
+            .collect()
+    }
+}
+
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl ToSql for SortDirection {
+    fn to_sql(&self) -> String {
+        match self {
+            SortDirection::Ascending => "ASC".to_string(),
+            SortDirection::Descending => "DESC".to_string(),
+        }
+    }
+}
+
+pub struct Sort<T>
+where
+    T: Fields + ToSql,
+{
+    pub field: T,
+    pub direction: SortDirection,
+}
+
+impl<T> ToSql for Sort<T>
+where
+    T: Fields + ToSql,
+{
+    fn to_sql(&self) -> String {
+        format!("{} {}", self.field.to_sql(), self.direction.to_sql())
+    }
+}
+
+/// Window of rows to return, `limit` rows skipping the first `offset` ones.
+pub struct Pagination {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+enum FilterToken {
+    Sql(String),
+    Parameter(FilterValue),
+    Group(Vec<FilterToken>),
+}
+
+/// Renders the tokens, numbering placeholders after the already collected parameters.
+fn render(tokens: &[FilterToken], parameters: &mut Vec<FilterValue>) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            FilterToken::Sql(sql) => sql.clone(),
+            FilterToken::Parameter(value) => {
+                parameters.push(value.clone());
+                format!("${}", parameters.len())
+            }
+            FilterToken::Group(group) => format!("({})", render(group, parameters)),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 pub struct Filters<T> 
 where T: Fields + ToSql 
 {
-    filter_sequences : Vec<String>,
+    filter_sequences : Vec<FilterToken>,
     fantom: PhantomData<T>,
 }
 
@@ -65,32 +240,65 @@ where T: Fields + ToSql {
         }
     }
 
-    pub(super) fn build(&self) -> String {
-        self.filter_sequences.join(" ")
+    pub fn is_empty(&self) -> bool {
+        self.filter_sequences.is_empty()
     }
-}
 
-impl<T> Filters<T> where T: Fields + ToSql {
-    pub fn and(&mut self, field: &T, operator: Operator, value: String) -> &mut Self {
-        if !self.filter_sequences.is_empty() {
-            self.filter_sequences.push("AND".to_string());
+    pub(super) fn build(&self) -> SqlFilter {
+        let mut parameters = vec![];
+        let condition = render(&self.filter_sequences, &mut parameters);
+        SqlFilter {
+            condition,
+            parameters,
         }
-        self.filter_sequences.push(field.to_sql());
-        self.filter_sequences.push(operator.to_sql());
-        self.filter_sequences.push(value.to_string());
-        self
     }
 
-    #[allow(dead_code)]
-    pub fn or(&mut self, field: &T, operator: Operator, value: String) -> &mut Self {
+    fn push(&mut self, binary_operator: BinaryOperator, tokens: Vec<FilterToken>) -> &mut Self {
         if !self.filter_sequences.is_empty() {
-            self.filter_sequences.push("OR".to_string());
+            self.filter_sequences
+                .push(FilterToken::Sql(binary_operator.to_sql()));
         }
-        self.filter_sequences.push(field.to_sql());
-        self.filter_sequences.push(operator.to_sql());
-        self.filter_sequences.push(value.to_string());
+        self.filter_sequences.extend(tokens);
         self
     }
+
+    fn condition(field: &T, operator: Operator, value: FilterValue) -> Vec<FilterToken> {
+        vec![
+            FilterToken::Sql(field.to_sql()),
+            FilterToken::Sql(operator.to_sql()),
+            FilterToken::Parameter(value),
+        ]
+    }
+
+    fn group(group: Filters<T>) -> Vec<FilterToken> {
+        vec![FilterToken::Group(group.filter_sequences)]
+    }
+}
+
+impl<T> Filters<T> where T: Fields + ToSql {
+    pub fn and(&mut self, field: &T, operator: Operator, value: impl Into<FilterValue>) -> &mut Self {
+        self.push(BinaryOperator::And, Self::condition(field, operator, value.into()))
+    }
+
+    pub fn or(&mut self, field: &T, operator: Operator, value: impl Into<FilterValue>) -> &mut Self {
+        self.push(BinaryOperator::Or, Self::condition(field, operator, value.into()))
+    }
+
+    /// Adds the whole group between parentheses, an empty group is ignored.
+    pub fn and_group(&mut self, group: Filters<T>) -> &mut Self {
+        if group.is_empty() {
+            return self;
+        }
+        self.push(BinaryOperator::And, Self::group(group))
+    }
+
+    /// Adds the whole group between parentheses, an empty group is ignored.
+    pub fn or_group(&mut self, group: Filters<T>) -> &mut Self {
+        if group.is_empty() {
+            return self;
+        }
+        self.push(BinaryOperator::Or, Self::group(group))
+    }
 }
 
 // Test filter builder
@@ -121,10 +329,72 @@ mod test {
         };
 
         filter_builder
-            .and(&StudentFields::Name, Operator::Equal, "`Alice`".to_string())
-            .or(&StudentFields::Age, Operator::GreaterThan, "20".to_string());
+            .and(&StudentFields::Name, Operator::Equal, "`Alice`")
+            .or(&StudentFields::Age, Operator::GreaterThan, 20);
+
+        let filter_sql = filter_builder.build();
+        assert_eq!(filter_sql.condition, "name = $1 OR age > $2");
+        assert_eq!(
+            filter_sql.parameters,
+            vec![
+                FilterValue::Text("`Alice`".to_string()),
+                FilterValue::Integer(20)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_builder_keeps_hostile_input_out_of_sql() {
+        let hostile = "2024-01-01'; DROP TABLE entries; --";
+        let mut filter_builder = Filters::<StudentFields>::new();
+
+        filter_builder
+            .and(&StudentFields::Name, Operator::Equal, hostile)
+            .and(&StudentFields::Name, Operator::NotEqual, "' OR '1'='1");
 
         let filter_sql = filter_builder.build();
-        assert_eq!(filter_sql, "name = `Alice` OR age > 20");
+        assert_eq!(filter_sql.condition, "name = $1 AND name != $2");
+        assert!(!filter_sql.condition.contains("DROP"));
+        assert_eq!(filter_sql.parameters[0], FilterValue::Text(hostile.to_string()));
+        assert_eq!(filter_sql.sql_parameters().len(), 2);
+    }
+
+    #[test]
+    fn test_filter_builder_groups() {
+        let mut names = Filters::<StudentFields>::new();
+        names
+            .and(&StudentFields::Name, Operator::Equal, "Alice")
+            .or(&StudentFields::Name, Operator::Equal, "Bob");
+
+        let mut filter_builder = Filters::<StudentFields>::new();
+        filter_builder
+            .and(&StudentFields::Age, Operator::GreaterThan, 20)
+            .and_group(names)
+            .and_group(Filters::new())
+            .or_group(Filters::new());
+
+        let filter_sql = filter_builder.build();
+        assert_eq!(filter_sql.condition, "age > $1 AND (name = $2 OR name = $3)");
+        assert_eq!(
+            filter_sql.parameters,
+            vec![
+                FilterValue::Integer(20),
+                FilterValue::Text("Alice".to_string()),
+                FilterValue::Text("Bob".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern("rent"), "%rent%");
+        assert_eq!(contains_pattern("100%_off\\"), "%100\\%\\_off\\\\%");
+    }
+
+    #[test]
+    fn test_empty_filter_builder() {
+        let filter_sql = Filters::<StudentFields>::new().build();
+        assert!(filter_sql.condition.is_empty());
+        assert!(filter_sql.parameters.is_empty());
     }
 }
\ No newline at end of file
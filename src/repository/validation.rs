@@ -0,0 +1,278 @@
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use serde::Deserialize;
+use tracing::Level;
+
+use crate::model::{account::Account, account::AccountFamily, money::Money, problem::FieldError};
+
+/// Oldest accepted event date by default, in days before today.
+const DEFAULT_MAX_PAST_DAYS: u64 = 10 * 365;
+
+/// Latest accepted event date by default, in days after today.
+const DEFAULT_MAX_FUTURE_DAYS: u64 = 365;
+
+/// Years accepted in any date, calendar arithmetic on them cannot overflow.
+pub const DATE_YEARS: RangeInclusive<i32> = 1..=9999;
+
+/// What to do with an entry matching a `FamilyRule`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Record the entry and log a warning.
+    Warn,
+    /// Reject the entry.
+    Deny,
+}
+
+/// Flags entries crediting a `credit` family account from a `debit` family one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FamilyRule {
+    pub credit: AccountFamily,
+    pub debit: AccountFamily,
+    pub action: RuleAction,
+}
+
+/// Double-entry rules checked before an entry is written, the `[validation]`
+/// section of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ValidationRules {
+    /// Oldest accepted event date, in days before today.
+    pub max_past_days: u64,
+    /// Latest accepted event date, in days after today.
+    pub max_future_days: u64,
+    pub family_rules: Vec<FamilyRule>,
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        ValidationRules {
+            max_past_days: DEFAULT_MAX_PAST_DAYS,
+            max_future_days: DEFAULT_MAX_FUTURE_DAYS,
+            family_rules: vec![],
+        }
+    }
+}
+
+impl ValidationRules {
+    pub fn check_amount(&self, field: &str, amount: Money) -> Option<FieldError> {
+        if amount <= Money::ZERO {
+            return Some(FieldError::new(field, "must be positive"));
+        }
+        None
+    }
+
+    pub fn check_event_date(
+        &self,
+        field: &str,
+        event_date: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Option<FieldError> {
+        let oldest = now - Days::new(self.max_past_days);
+        let latest = now + Days::new(self.max_future_days);
+        if event_date < oldest || event_date > latest {
+            return Some(FieldError::new(
+                field,
+                format!(
+                    "must be within {} days before and {} days after today",
+                    self.max_past_days, self.max_future_days
+                ),
+            ));
+        }
+        None
+    }
+
+    /// Days with no event date window, such as the end of a recurrence, only need
+    /// to stay within `DATE_YEARS`.
+    pub fn check_day(&self, field: &str, day: NaiveDate) -> Option<FieldError> {
+        if !DATE_YEARS.contains(&day.year()) {
+            return Some(FieldError::new(
+                field,
+                format!(
+                    "must be within years {} to {}",
+                    DATE_YEARS.start(),
+                    DATE_YEARS.end()
+                ),
+            ));
+        }
+        None
+    }
+
+    /// Checks the credit and debit accounts of an entry, as recorded in the database.
+    /// Accounts matching a warn rule are only logged.
+    pub fn check_accounts(&self, credit: &Account, debit: &Account) -> Vec<FieldError> {
+        let postings = [
+            ("credit".to_string(), credit, Money::from_cents(1)),
+            ("debit".to_string(), debit, Money::from_cents(-1)),
+        ];
+        self.check_postings(&postings, true)
+    }
+
+    /// Same as `check_accounts` for a transaction, every credited account against every
+    /// debited one. `postings` holds the field naming each posting account, the account
+    /// and the posted amount. Errors name the debited posting. Reversals skip the family
+    /// rules, they undo a transaction that went through them.
+    pub fn check_postings(
+        &self,
+        postings: &[(String, &Account, Money)],
+        family_rules: bool,
+    ) -> Vec<FieldError> {
+        let credited = postings.iter().filter(|(_, _, amount)| *amount > Money::ZERO);
+        let mut errors = vec![];
+        for (_, credit, _) in credited {
+            let debited = postings.iter().filter(|(_, _, amount)| *amount < Money::ZERO);
+            for (field, debit, _) in debited {
+                if credit.id == debit.id {
+                    errors.push(FieldError::new(field, "must differ from the credit account"));
+                } else if family_rules {
+                    errors.extend(self.check_families(field, credit, debit));
+                }
+            }
+        }
+        errors
+    }
+
+    fn check_families(&self, field: &str, credit: &Account, debit: &Account) -> Vec<FieldError> {
+        let mut errors = vec![];
+        let rules = self
+            .family_rules
+            .iter()
+            .filter(|rule| rule.credit == credit.family && rule.debit == debit.family);
+        for rule in rules {
+            let message = format!(
+                "crediting {:?} account '{}' from {:?} account '{}'",
+                credit.family,
+                credit.reference(),
+                debit.family,
+                debit.reference()
+            );
+            match rule.action {
+                RuleAction::Warn => tracing::event!(Level::WARN, "Odd entry: {}", message),
+                RuleAction::Deny => {
+                    errors.push(FieldError::new(field, format!("{} is not allowed", message)))
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn account(id: i32, name: &str, family: AccountFamily) -> Account {
+        Account {
+            id: Some(id),
+            name: name.to_string(),
+            family,
+            parent_id: None,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn test_amount_and_event_date_ranges() {
+        let rules = ValidationRules {
+            max_past_days: 30,
+            max_future_days: 7,
+            family_rules: vec![],
+        };
+        let now = Utc::now();
+
+        assert_eq!(rules.check_amount("amount", Money::from_cents(1)), None);
+        assert_eq!(
+            rules.check_amount("amount", Money::ZERO),
+            Some(FieldError::new("amount", "must be positive"))
+        );
+
+        assert_eq!(rules.check_event_date("event_date", now, now), None);
+        assert_eq!(
+            rules.check_event_date("event_date", now - Days::new(30), now),
+            None
+        );
+        assert!(
+            rules
+                .check_event_date("event_date", now - Days::new(31), now)
+                .is_some()
+        );
+        assert!(
+            rules
+                .check_event_date("event_date", now + Days::new(8), now)
+                .is_some()
+        );
+
+        let day = |value: &str| value.parse::<NaiveDate>().unwrap();
+        assert_eq!(rules.check_day("end_date", day("9999-12-31")), None);
+        assert!(rules.check_day("end_date", day("+10000-01-01")).is_some());
+        assert!(rules.check_day("end_date", day("0000-12-31")).is_some());
+    }
+
+    #[test]
+    fn test_account_rules() {
+        let rules = ValidationRules {
+            family_rules: vec![
+                FamilyRule {
+                    credit: AccountFamily::Expense,
+                    debit: AccountFamily::Revenue,
+                    action: RuleAction::Deny,
+                },
+                FamilyRule {
+                    credit: AccountFamily::Expense,
+                    debit: AccountFamily::Liability,
+                    action: RuleAction::Warn,
+                },
+            ],
+            ..ValidationRules::default()
+        };
+        let bank = account(1, "Bank", AccountFamily::Asset);
+        let food = account(2, "Food", AccountFamily::Expense);
+        let salary = account(3, "Salary", AccountFamily::Revenue);
+        let card = account(4, "Card", AccountFamily::Liability);
+
+        assert_eq!(rules.check_accounts(&food, &bank), vec![]);
+        assert_eq!(
+            rules.check_accounts(&bank, &bank),
+            vec![FieldError::new(
+                "debit",
+                "must differ from the credit account"
+            )]
+        );
+        assert_eq!(rules.check_accounts(&food, &salary).len(), 1);
+        assert_eq!(rules.check_accounts(&food, &card), vec![]);
+        assert_eq!(rules.check_accounts(&salary, &food), vec![]);
+
+        // A split transaction paid by the bank and, against the rules, by the salary
+        let posting = |index: usize, account, amount: i64| {
+            (format!("postings[{}].account", index), account, Money::from_cents(amount))
+        };
+        let postings = [
+            posting(0, &food, 5000),
+            posting(1, &bank, -3000),
+            posting(2, &salary, -2000),
+        ];
+        assert_eq!(
+            rules.check_postings(&postings, true),
+            vec![FieldError::new(
+                "postings[2].account",
+                "crediting Expense account 'Food' from Revenue account 'Salary' is not allowed"
+            )]
+        );
+        // Reversing an allowed transaction swaps its sides into a denied pair
+        let allowed = [posting(0, &salary, 2000), posting(1, &food, -2000)];
+        assert_eq!(rules.check_postings(&allowed, true), vec![]);
+        let reversal = [posting(0, &salary, -2000), posting(1, &food, 2000)];
+        assert_eq!(rules.check_postings(&reversal, true).len(), 1);
+        assert_eq!(rules.check_postings(&reversal, false), vec![]);
+
+        let same_account = [posting(0, &bank, 1000), posting(1, &bank, -1000)];
+        assert_eq!(
+            rules.check_postings(&same_account, false),
+            vec![FieldError::new(
+                "postings[1].account",
+                "must differ from the credit account"
+            )]
+        );
+    }
+}
@@ -1,39 +1,217 @@
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use rocket::{http::Status, serde::json::Json};
+use rocket::{
+    Request, Response, Shutdown,
+    http::{ContentType, Status},
+    response::{
+        self, Responder,
+        status::Created,
+        stream::{Event, EventStream},
+    },
+    serde::json::Json,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::Level;
 
-use utoipa::OpenApi;
+use utoipa::openapi::{self, ResponseBuilder};
+use utoipa::{IntoParams, Modify, OpenApi};
 
 use crate::{
-    model,
-    repository::{self, Repository},
+    model::{self, problem::Problem},
+    repository::{self, Repository, error::RepositoryError},
+    utils,
 };
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         get_account,
+        get_accounts,
+        get_account_tree,
         create_account,
+        update_account,
+        patch_account,
+        delete_account,
+        get_account_balance,
+        get_balances,
+        get_balance_sheet,
+        get_income_statement,
+        get_trial_balance,
+        get_cash_flow,
+        get_net_worth,
+        get_account_ledger,
         get_entry,
         create_entry,
+        update_entry,
+        delete_entry,
+        reverse_entry,
         get_entries_from_date_to_date,
+        get_transaction,
+        get_transactions,
+        create_transaction,
+        update_transaction,
+        delete_transaction,
+        get_recurrence,
+        get_recurrences,
+        create_recurrence,
+        update_recurrence,
+        delete_recurrence,
+        get_budget,
+        get_budgets,
+        create_budget,
+        update_budget,
+        delete_budget,
+        get_budget_status,
+        get_envelopes,
+        sweep_envelopes,
+        get_envelope_transfer,
+        get_envelope_transfers,
+        create_envelope_transfer,
+        delete_envelope_transfer,
+        get_events,
     ),
     components(
-        schemas(model::account::Account, model::entry::Entry, model::account::AccountFamily)
+        schemas(
+            model::account::Account,
+            model::account::AccountPatch,
+            model::entry::Entry,
+            model::entry::EntryPage,
+            model::transaction::Transaction,
+            model::transaction::Posting,
+            model::transaction::TransactionPage,
+            model::account::AccountFamily,
+            model::money::Money,
+            model::balance::AccountBalance,
+            model::balance::FamilyBalances,
+            model::balance::AccountTree,
+            model::report::BalanceSheet,
+            model::report::IncomeStatement,
+            model::report::StatementSection,
+            model::report::StatementLine,
+            model::report::ReportPeriod,
+            model::report::ReportTable,
+            model::report::TrialBalance,
+            model::report::TrialBalanceLine,
+            model::report::BalanceMismatch,
+            model::report::CashFlowStatement,
+            model::report::CashFlowSection,
+            model::report::CashFlowLine,
+            model::report::NetWorthSeries,
+            model::report::NetWorthPoint,
+            model::report::FamilyTotal,
+            model::ledger::Ledger,
+            model::ledger::LedgerLine,
+            model::recurrence::Recurrence,
+            model::recurrence::Frequence,
+            model::budget::Budget,
+            model::budget::BudgetStatus,
+            model::envelope::EnvelopeTransfer,
+            model::envelope::Envelope,
+            model::envelope::EnvelopeMonth,
+            model::event::LedgerEvent,
+            model::event::Movement,
+            model::problem::Problem,
+            model::problem::FieldError,
+        )
     ),
+    modifiers(&ServerProblems),
     tags(
         (name = "finance", description = "Finance management API")
     )
 )]
 pub struct ApiDoc;
 
+/// Documents the 500 and 503 problems any operation may answer.
+struct ServerProblems;
+
+impl Modify for ServerProblems {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        for path in openapi.paths.paths.values_mut() {
+            for operation in path.operations.values_mut() {
+                for (status, description) in [
+                    ("500", "Unexpected server error"),
+                    ("503", "Database unavailable"),
+                ] {
+                    let response = ResponseBuilder::new()
+                        .description(description)
+                        .content(
+                            "application/problem+json",
+                            openapi::ContentBuilder::new()
+                                .schema(openapi::Ref::from_schema_name("Problem"))
+                                .build(),
+                        )
+                        .build();
+                    operation
+                        .responses
+                        .responses
+                        .entry(status.to_string())
+                        .or_insert(response.into());
+                }
+            }
+        }
+    }
+}
+
+/// Problem with the reason phrase of the status as title.
+fn problem(status: Status, detail: impl Into<String>) -> Problem {
+    Problem::new(
+        status.code,
+        status.reason().unwrap_or_default(),
+        Some(detail.into()),
+    )
+}
+
+impl<'r> Responder<'r, 'static> for Problem {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = Status::from_code(self.status).unwrap_or(Status::InternalServerError);
+        Response::build_from(Json(self).respond_to(request)?)
+            .status(status)
+            .header(ContentType::new("application", "problem+json"))
+            .ok()
+    }
+}
+
+impl From<RepositoryError> for Problem {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound(detail) => problem(Status::NotFound, detail),
+            RepositoryError::Validation(detail) => problem(Status::BadRequest, detail),
+            RepositoryError::InvalidFields(errors) => Problem {
+                errors,
+                ..problem(Status::BadRequest, "Some fields break the validation rules")
+            },
+            RepositoryError::Conflict(detail) | RepositoryError::InUse(detail) => {
+                problem(Status::Conflict, detail)
+            }
+            RepositoryError::Unavailable(detail) => {
+                tracing::event!(Level::ERROR, "Database unavailable: {}", detail);
+                problem(
+                    Status::ServiceUnavailable,
+                    "The database cannot be reached, retry later",
+                )
+            }
+            RepositoryError::Internal(detail) => {
+                tracing::event!(Level::ERROR, "Internal error: {}", detail);
+                Problem::new(500, "Internal Server Error", None)
+            }
+        }
+    }
+}
+
+/// Answers the requests failing before reaching a handler, e.g. a malformed body,
+/// with a problem instead of Rocket's HTML page.
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> Problem {
+    Problem::new(status.code, status.reason().unwrap_or_default(), None)
+}
+
 #[utoipa::path(
     get,
     path = "/account/{id}",
     responses(
         (status = 200, description = "Account found successfully", body = Account),
-        (status = 404, description = "Account not found")
+        (status = 404, description = "Account not found", body = Problem, content_type = "application/problem+json")
     ),
     params(
         ("id" = i32, Path, description = "Account id")
@@ -43,12 +221,39 @@ pub struct ApiDoc;
 pub async fn get_account(
     id: i32,
     repository: &rocket::State<Arc<Mutex<Repository>>>,
-) -> Result<Json<model::account::Account>, Status> {
-    // Clone the Arc to avoid holding the MutexGuard across await
-    match repository.lock().await.get_account(id).await {
-        Ok(entry) => Ok(Json(entry)),
-        Err(_) => Err(Status::NotFound),
-    }
+) -> Result<Json<model::account::Account>, Problem> {
+    let account = repository.lock().await.get_account(id).await?;
+    Ok(Json(account))
+}
+
+#[utoipa::path(
+    get,
+    path = "/accounts",
+    responses(
+        (status = 200, description = "Accounts retrieved successfully", body = [Account])
+    )
+)]
+#[get("/accounts")]
+pub async fn get_accounts(
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<Vec<model::account::Account>>, Problem> {
+    let accounts = repository.lock().await.get_accounts().await?;
+    Ok(Json(accounts))
+}
+
+#[utoipa::path(
+    get,
+    path = "/accounts/tree",
+    responses(
+        (status = 200, description = "Top-level accounts with their sub-accounts nested, balances rolled up in subtotals", body = [AccountTree])
+    )
+)]
+#[get("/accounts/tree")]
+pub async fn get_account_tree(
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<Vec<model::balance::AccountTree>>, Problem> {
+    let tree = repository.lock().await.get_account_tree().await?;
+    Ok(Json(tree))
 }
 
 #[utoipa::path(
@@ -56,23 +261,410 @@ pub async fn get_account(
     path = "/account",
     request_body = Account,
     responses(
-        (status = 201, description = "Account created successfully"),
+        (status = 201, description = "Account created successfully", body = Account,
+            headers(("Location" = String, description = "Path of the created account"))),
+        (status = 400, description = "Name containing ':', or parent account missing or in another family", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A sibling account already has this name", body = Problem, content_type = "application/problem+json")
     )
 )]
 #[post("/account", data = "<account>")]
 pub async fn create_account(
     account: Json<model::account::Account>,
     repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
-) -> Status {
-    match repository
+) -> Result<Created<Json<model::account::Account>>, Problem> {
+    let account = repository
         .lock()
         .await
         .insert_account(&account.into_inner())
+        .await?;
+    let location = uri!(get_account(account.id.unwrap_or_default()));
+    Ok(Created::new(location.to_string()).body(Json(account)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/account/{id}",
+    request_body = Account,
+    responses(
+        (status = 200, description = "Account updated successfully", body = Account),
+        (status = 400, description = "Name containing ':', parent account missing, in another family or among the sub-accounts, or family change of an account with sub-accounts", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A sibling account already has this name", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Account id")
+    )
+)]
+#[put("/account/<id>", data = "<account>")]
+pub async fn update_account(
+    id: i32,
+    account: Json<model::account::Account>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::account::Account>, Problem> {
+    let account = repository
+        .lock()
+        .await
+        .update_account(id, &account.into_inner())
+        .await?;
+    account
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Account {} not found", id)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/account/{id}",
+    request_body = AccountPatch,
+    responses(
+        (status = 200, description = "Account updated successfully", body = Account),
+        (status = 400, description = "Name containing ':', parent account missing, in another family or among the sub-accounts, or family change of an account with sub-accounts", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A sibling account already has this name", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Account id")
+    )
+)]
+#[patch("/account/<id>", data = "<patch>")]
+pub async fn patch_account(
+    id: i32,
+    patch: Json<model::account::AccountPatch>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::account::Account>, Problem> {
+    let account = repository
+        .lock()
+        .await
+        .patch_account(id, &patch.into_inner())
+        .await?;
+    account
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Account {} not found", id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/account/{id}",
+    responses(
+        (status = 204, description = "Account deleted successfully"),
+        (status = 404, description = "Account not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Account is still referenced by entries or sub-accounts", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Account id")
+    )
+)]
+#[delete("/account/<id>")]
+pub async fn delete_account(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Status, Problem> {
+    match repository.lock().await.delete_account(id).await? {
+        true => Ok(Status::NoContent),
+        false => Err(problem(
+            Status::NotFound,
+            format!("Account {} not found", id),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/account/{id}/balance",
+    responses(
+        (status = 200, description = "Account balance found successfully", body = AccountBalance),
+        (status = 404, description = "Account not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Account id")
+    )
+)]
+#[get("/account/<id>/balance")]
+pub async fn get_account_balance(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::balance::AccountBalance>, Problem> {
+    let balance = repository.lock().await.get_account_balance(id).await?;
+    balance
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Account {} not found", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/balances",
+    responses(
+        (status = 200, description = "Balances retrieved successfully, grouped by account family", body = [FamilyBalances])
+    )
+)]
+#[get("/balances")]
+pub async fn get_balances(
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<Vec<model::balance::FamilyBalances>>, Problem> {
+    let balances = repository.lock().await.get_balances().await?;
+    Ok(Json(balances))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/balance-sheet",
+    responses(
+        (status = 200, description = "Balance sheet computed successfully", body = BalanceSheet),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("as_of" = Option<String>, Query, description = "Last day of the entries taken into account (inclusive), RFC 3339 or YYYY-MM-DD, now by default")
+    )
+)]
+#[get("/reports/balance-sheet?<as_of>")]
+pub async fn get_balance_sheet(
+    as_of: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::report::BalanceSheet>, Problem> {
+    let as_of = match as_of {
+        Some(as_of) => parse_date(&as_of, true)?,
+        None => chrono::Utc::now(),
+    };
+
+    let balance_sheet = repository.lock().await.get_balance_sheet(as_of).await?;
+    Ok(Json(balance_sheet))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/trial-balance",
+    responses(
+        (status = 200, description = "Trial balance computed successfully, see `balanced` and `mismatches` for integrity issues", body = TrialBalance),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("as_of" = Option<String>, Query, description = "Last day of the entries taken into account (inclusive), RFC 3339 or YYYY-MM-DD, now by default")
+    )
+)]
+#[get("/reports/trial-balance?<as_of>")]
+pub async fn get_trial_balance(
+    as_of: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::report::TrialBalance>, Problem> {
+    let as_of = match as_of {
+        Some(as_of) => parse_date(&as_of, true)?,
+        None => chrono::Utc::now(),
+    };
+
+    let trial_balance = repository.lock().await.get_trial_balance(as_of).await?;
+    Ok(Json(trial_balance))
+}
+
+/// Most columns a report may have, each one costs a query.
+const MAX_REPORT_PERIODS: usize = 400;
+
+/// Splits the report dates along the grouping, see `model::report::ReportPeriod::split`.
+/// Reports run from January 1st to today by default.
+fn report_periods(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    group_by: Option<String>,
+) -> Result<Vec<model::report::ReportPeriod>, Problem> {
+    use chrono::Datelike;
+    use model::recurrence::Frequence;
+
+    let end_date = match end_date {
+        Some(end_date) => parse_date(&end_date, true)?.date_naive(),
+        None => chrono::Utc::now().date_naive(),
+    };
+    let start_date = match start_date {
+        Some(start_date) => parse_date(&start_date, false)?.date_naive(),
+        None => end_date.with_ordinal(1).unwrap_or(end_date),
+    };
+    if start_date > end_date {
+        let detail = format!("start_date {} is after end_date {}", start_date, end_date);
+        return Err(problem(Status::BadRequest, detail));
+    }
+
+    let grouping = match group_by.as_deref() {
+        None => None,
+        Some("day") => Some(Frequence::Daily),
+        Some("week") => Some(Frequence::Weekly),
+        Some("month") => Some(Frequence::Monthly),
+        Some("year") => Some(Frequence::Yearly),
+        Some(group_by) => {
+            let detail = format!(
+                "Unknown period '{}', expected day, week, month or year",
+                group_by
+            );
+            return Err(problem(Status::BadRequest, detail));
+        }
+    };
+    // Stops one period past the limit, a wide range grouped by day is never built
+    let periods: Vec<_> = model::report::ReportPeriod::split(start_date, end_date, grouping)
+        .take(MAX_REPORT_PERIODS + 1)
+        .collect();
+    if periods.len() > MAX_REPORT_PERIODS {
+        let detail = format!(
+            "More than {} periods requested, at most {} are allowed",
+            MAX_REPORT_PERIODS, MAX_REPORT_PERIODS
+        );
+        return Err(problem(Status::BadRequest, detail));
+    }
+    Ok(periods)
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/income-statement",
+    responses(
+        (status = 200, description = "Income statement computed successfully", body = IncomeStatement),
+        (status = 400, description = "Malformed date or grouping, start after end, or too many periods", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("start_date" = Option<String>, Query, description = "First day of the report, RFC 3339 or YYYY-MM-DD, January 1st of the end date year by default"),
+        ("end_date" = Option<String>, Query, description = "Last day of the report (inclusive), RFC 3339 or YYYY-MM-DD, today by default"),
+        ("group_by" = Option<String>, Query, description = "One column per day, week, month or year, a single column for the whole range by default")
+    )
+)]
+#[get("/reports/income-statement?<start_date>&<end_date>&<group_by>")]
+pub async fn get_income_statement(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    group_by: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::report::IncomeStatement>, Problem> {
+    let periods = report_periods(start_date, end_date, group_by)?;
+
+    let income_statement = repository
+        .lock()
+        .await
+        .get_income_statement(periods)
+        .await?;
+    Ok(Json(income_statement))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/cash-flow",
+    responses(
+        (status = 200, description = "Cash flow statement computed successfully", body = CashFlowStatement),
+        (status = 400, description = "Malformed date or grouping, start after end, too many periods, or unknown or non Asset account", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("start_date" = Option<String>, Query, description = "First day of the report, RFC 3339 or YYYY-MM-DD, January 1st of the end date year by default"),
+        ("end_date" = Option<String>, Query, description = "Last day of the report (inclusive), RFC 3339 or YYYY-MM-DD, today by default"),
+        ("group_by" = Option<String>, Query, description = "One column per day, week, month or year, a single column for the whole range by default"),
+        ("accounts" = Option<Vec<i32>>, Query, description = "Id of an Asset account to follow with its sub-accounts, repeat to follow several, every Asset account by default")
+    )
+)]
+#[get("/reports/cash-flow?<start_date>&<end_date>&<group_by>&<accounts>")]
+pub async fn get_cash_flow(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    group_by: Option<String>,
+    accounts: Vec<i32>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::report::CashFlowStatement>, Problem> {
+    let periods = report_periods(start_date, end_date, group_by)?;
+
+    let cash_flow = repository
+        .lock()
         .await
-    {
-        Ok(_) => Status::Created,
-        Err(_) => Status::InternalServerError,
+        .get_cash_flow(&accounts, periods)
+        .await?;
+    Ok(Json(cash_flow))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/net-worth",
+    responses(
+        (status = 200, description = "Net worth computed successfully", body = NetWorthSeries),
+        (status = 400, description = "Malformed date or interval, start after end, or too many periods", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("start_date" = Option<String>, Query, description = "First day of the series, RFC 3339 or YYYY-MM-DD, January 1st of the end date year by default"),
+        ("end_date" = Option<String>, Query, description = "Last day of the series (inclusive), RFC 3339 or YYYY-MM-DD, today by default"),
+        ("interval" = Option<String>, Query, description = "One point at the end of each day, week, month or year, month by default")
+    )
+)]
+#[get("/reports/net-worth?<start_date>&<end_date>&<interval>")]
+pub async fn get_net_worth(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    interval: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::report::NetWorthSeries>, Problem> {
+    let interval = interval.unwrap_or_else(|| "month".to_string());
+    let periods = report_periods(start_date, end_date, Some(interval))?;
+
+    let net_worth = repository.lock().await.get_net_worth(periods).await?;
+    Ok(Json(net_worth))
+}
+
+#[utoipa::path(
+    get,
+    path = "/account/{id}/ledger",
+    responses(
+        (status = 200, description = "Account ledger retrieved successfully", body = Ledger),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Account id"),
+        ("start_date" = Option<String>, Query, description = "First day of the ledger, RFC 3339 or YYYY-MM-DD"),
+        ("end_date" = Option<String>, Query, description = "Last day of the ledger (inclusive), RFC 3339 or YYYY-MM-DD")
+    )
+)]
+#[get("/account/<id>/ledger?<start_date>&<end_date>")]
+pub async fn get_account_ledger(
+    id: i32,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::ledger::Ledger>, Problem> {
+    let start_date = start_date
+        .map(|date| parse_date(&date, false))
+        .transpose()?;
+    let end_date = end_date.map(|date| parse_date(&date, true)).transpose()?;
+
+    let ledger = repository
+        .lock()
+        .await
+        .get_account_ledger(id, start_date, end_date)
+        .await?;
+    ledger
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Account {} not found", id)))
+}
+
+/// Day of a date parameter, today when missing.
+fn parse_optional_day(value: Option<String>) -> Result<chrono::NaiveDate, Problem> {
+    match value {
+        Some(value) => Ok(parse_date(&value, false)?.date_naive()),
+        None => Ok(chrono::Utc::now().date_naive()),
+    }
+}
+
+/// Parses a date query parameter, see `utils::parse_date_param`.
+fn parse_date(value: &str, end_of_day: bool) -> Result<chrono::DateTime<chrono::Utc>, Problem> {
+    use chrono::Datelike;
+    use repository::validation::DATE_YEARS;
+
+    let date = utils::parse_date_param(value, end_of_day).map_err(|_| {
+        problem(
+            Status::BadRequest,
+            format!(
+                "Malformed date '{}', expected RFC 3339 or YYYY-MM-DD",
+                value
+            ),
+        )
+    })?;
+    if !DATE_YEARS.contains(&date.year()) {
+        let detail = format!(
+            "Date '{}' is out of range, years {} to {} are accepted",
+            value,
+            DATE_YEARS.start(),
+            DATE_YEARS.end()
+        );
+        return Err(problem(Status::BadRequest, detail));
     }
+    Ok(date)
 }
 
 #[utoipa::path(
@@ -80,7 +672,7 @@ pub async fn create_account(
     path = "/entry/{id}",
     responses(
         (status = 200, description = "Entry found successfully", body = Entry),
-        (status = 404, description = "Entry not found")
+        (status = 404, description = "Entry not found, or split across more than two accounts", body = Problem, content_type = "application/problem+json")
     ),
     params(
         ("id" = i32, Path, description = "Entry id")
@@ -90,11 +682,9 @@ pub async fn create_account(
 pub async fn get_entry(
     id: i32,
     repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
-) -> Result<Json<model::entry::Entry>, Status> {
-    match repository.lock().await.get_entry(id).await {
-        Ok(entry) => Ok(Json(entry)),
-        Err(_) => Err(Status::NotFound),
-    }
+) -> Result<Json<model::entry::Entry>, Problem> {
+    let entry = repository.lock().await.get_entry(id).await?;
+    Ok(Json(entry))
 }
 
 #[utoipa::path(
@@ -102,64 +692,887 @@ pub async fn get_entry(
     path = "/entry",
     request_body = Entry,
     responses(
-        (status = 201, description = "Entry created successfully"),
+        (status = 201, description = "Entry created successfully", body = Entry,
+            headers(("Location" = String, description = "Path of the created entry"))),
+        (status = 400, description = "Entry breaking the validation rules, listed in `errors`", body = Problem, content_type = "application/problem+json")
     )
 )]
 #[post("/entry", data = "<entry>")]
 pub async fn create_entry(
     entry: Json<model::entry::Entry>,
     repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
-) -> Status {
-    match repository
+) -> Result<Created<Json<model::entry::Entry>>, Problem> {
+    let entry = repository
         .lock()
         .await
         .insert_entry(&entry.into_inner())
+        .await?;
+    let location = uri!(get_entry(entry.id.unwrap_or_default()));
+    Ok(Created::new(location.to_string()).body(Json(entry)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/entry/{id}",
+    request_body = Entry,
+    responses(
+        (status = 200, description = "Entry updated successfully", body = Entry),
+        (status = 400, description = "Entry breaking the validation rules, listed in `errors`", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Entry not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Entry split across more than two postings, replace it with PUT /transaction/{id}", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Entry id")
+    )
+)]
+#[put("/entry/<id>", data = "<entry>")]
+pub async fn update_entry(
+    id: i32,
+    entry: Json<model::entry::Entry>,
+    repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
+) -> Result<Json<model::entry::Entry>, Problem> {
+    let entry = repository
+        .lock()
         .await
-    {
-        Ok(_) => Status::Created,
-        Err(_) => Status::InternalServerError,
-    }
+        .update_entry(id, &entry.into_inner())
+        .await?;
+    entry
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Entry {} not found", id)))
 }
 
 #[utoipa::path(
-    get,
-    path = "/entries",
+    delete,
+    path = "/entry/{id}",
     responses(
-        (status = 200, description = "Entries retrieved successfully", body = [Entry]),
-        (status = 500, description = "Internal server error")
+        (status = 204, description = "Entry deleted successfully"),
+        (status = 404, description = "Entry not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Entry has a reversal referencing it", body = Problem, content_type = "application/problem+json")
     ),
     params(
-        ("start_date" = Option<String>, Query, description = "Start date for filtering entries"),
-        ("end_date" = Option<String>, Query, description = "End date for filtering entries")
+        ("id" = i32, Path, description = "Entry id")
     )
 )]
-#[get("/entries?<start_date>&<end_date>")]
-pub async fn get_entries_from_date_to_date(
+#[delete("/entry/<id>")]
+pub async fn delete_entry(
+    id: i32,
     repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
-    start_date: Option<String>,
-    end_date: Option<String>,
-) -> Result<Json<Vec<model::entry::Entry>>, Status> {
-    let mut filters = repository::filter::Filters::<repository::filter::EntryFields>::new();
-    if let Some(start) = start_date {
-        filters.and(
-            &repository::filter::EntryFields::EventDate,
-            repository::filter::Operator::GreaterThanOrEqual,
-            start,
-        );
+) -> Result<Status, Problem> {
+    match repository.lock().await.delete_entry(id).await? {
+        true => Ok(Status::NoContent),
+        false => Err(problem(Status::NotFound, format!("Entry {} not found", id))),
     }
-    if let Some(end) = end_date {
-        filters.and(
-            &repository::filter::EntryFields::EventDate,
-            repository::filter::Operator::LessThanOrEqual,
-            end,
-        );
+}
+
+#[utoipa::path(
+    post,
+    path = "/entry/{id}/reverse",
+    responses(
+        (status = 201, description = "Reversal transaction created successfully, every posting negated", body = Transaction,
+            headers(("Location" = String, description = "Path of the reversal transaction"))),
+        (status = 404, description = "Entry not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Entry has already been reversed", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id of the entry to reverse")
+    )
+)]
+#[post("/entry/<id>/reverse")]
+pub async fn reverse_entry(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
+) -> Result<Created<Json<model::transaction::Transaction>>, Problem> {
+    let reversal = repository.lock().await.reverse_entry(id).await?;
+    match reversal {
+        Some(reversal) => {
+            let location = uri!(get_transaction(reversal.id.unwrap_or_default()));
+            Ok(Created::new(location.to_string()).body(Json(reversal)))
+        }
+        None => Err(problem(Status::NotFound, format!("Entry {} not found", id))),
     }
+}
+
+#[utoipa::path(
+    get,
+    path = "/transaction/{id}",
+    responses(
+        (status = 200, description = "Transaction found successfully, any entry is one", body = Transaction),
+        (status = 404, description = "Transaction not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Transaction id, same as the entry id")
+    )
+)]
+#[get("/transaction/<id>")]
+pub async fn get_transaction(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::transaction::Transaction>, Problem> {
+    let transaction = repository.lock().await.get_transaction(id).await?;
+    Ok(Json(transaction))
+}
+
+#[utoipa::path(
+    post,
+    path = "/transaction",
+    request_body = Transaction,
+    responses(
+        (status = 201, description = "Transaction created successfully", body = Transaction,
+            headers(("Location" = String, description = "Path of the created transaction"))),
+        (status = 400, description = "Transaction breaking the validation rules, listed in `errors`", body = Problem, content_type = "application/problem+json")
+    )
+)]
+#[post("/transaction", data = "<transaction>")]
+pub async fn create_transaction(
+    transaction: Json<model::transaction::Transaction>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Created<Json<model::transaction::Transaction>>, Problem> {
+    let transaction = repository
+        .lock()
+        .await
+        .insert_transaction(&transaction.into_inner())
+        .await?;
+    let location = uri!(get_transaction(transaction.id.unwrap_or_default()));
+    Ok(Created::new(location.to_string()).body(Json(transaction)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/transaction/{id}",
+    request_body = Transaction,
+    responses(
+        (status = 200, description = "Transaction updated successfully", body = Transaction),
+        (status = 400, description = "Transaction breaking the validation rules, listed in `errors`", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Transaction not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Transaction id")
+    )
+)]
+#[put("/transaction/<id>", data = "<transaction>")]
+pub async fn update_transaction(
+    id: i32,
+    transaction: Json<model::transaction::Transaction>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::transaction::Transaction>, Problem> {
+    let transaction = repository
+        .lock()
+        .await
+        .update_transaction(id, &transaction.into_inner())
+        .await?;
+    transaction
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Transaction {} not found", id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/transaction/{id}",
+    responses(
+        (status = 204, description = "Transaction deleted successfully"),
+        (status = 404, description = "Transaction not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Transaction has a reversal referencing it", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Transaction id")
+    )
+)]
+#[delete("/transaction/<id>")]
+pub async fn delete_transaction(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Status, Problem> {
+    match repository.lock().await.delete_entry(id).await? {
+        true => Ok(Status::NoContent),
+        false => Err(problem(
+            Status::NotFound,
+            format!("Transaction {} not found", id),
+        )),
+    }
+}
+
+/// Criteria of the `/entries` search, all optional.
+#[derive(Debug, FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EntrySearch {
+    /// Start date for filtering entries, RFC 3339 or YYYY-MM-DD
+    start_date: Option<String>,
+    /// End date for filtering entries (inclusive), RFC 3339 or YYYY-MM-DD
+    end_date: Option<String>,
+    /// Id of the credited account
+    credit: Option<i32>,
+    /// Id of the debited account
+    debit: Option<i32>,
+    /// Id of an account on either side of the entry, repeat to match any of several accounts
+    #[param(value_type = Option<Vec<i32>>)]
+    account: Vec<i32>,
+    /// Family of an account on either side of the entry, repeat to match any of several families
+    #[param(value_type = Option<Vec<String>>)]
+    family: Vec<String>,
+    /// Minimal amount (inclusive)
+    min_amount: Option<String>,
+    /// Maximal amount (inclusive)
+    max_amount: Option<String>,
+    /// Case-insensitive substring of the description
+    description: Option<String>,
+    /// Match entries satisfying any of the criteria above instead of all of them,
+    /// the date range always applies
+    any: Option<bool>,
+}
+
+impl EntrySearch {
+    fn to_filters(
+        &self,
+    ) -> Result<repository::filter::Filters<repository::filter::EntryFields>, Problem> {
+        use repository::filter::{EntryFields, Filters, Operator};
+
+        let mut groups: Vec<Filters<EntryFields>> = vec![];
+
+        if let Some(credit) = self.credit {
+            let mut group = Filters::new();
+            group.and(&EntryFields::CreditAccount, Operator::Equal, credit);
+            groups.push(group);
+        }
+        if let Some(debit) = self.debit {
+            let mut group = Filters::new();
+            group.and(&EntryFields::DebitAccount, Operator::Equal, debit);
+            groups.push(group);
+        }
+        if !self.account.is_empty() {
+            let mut group = Filters::new();
+            for account in &self.account {
+                group
+                    .or(&EntryFields::CreditAccount, Operator::Equal, *account)
+                    .or(&EntryFields::DebitAccount, Operator::Equal, *account);
+            }
+            groups.push(group);
+        }
+        if !self.family.is_empty() {
+            let mut group = Filters::new();
+            for family in &self.family {
+                let family: model::account::AccountFamily = family
+                    .parse()
+                    .map_err(|e: String| problem(Status::BadRequest, e))?;
+                group
+                    .or(&EntryFields::CreditFamily, Operator::Equal, &family)
+                    .or(&EntryFields::DebitFamily, Operator::Equal, &family);
+            }
+            groups.push(group);
+        }
+        if let Some(min_amount) = &self.min_amount {
+            let min_amount: model::money::Money =
+                min_amount.parse().map_err(|e: model::money::MoneyError| {
+                    problem(Status::BadRequest, e.to_string())
+                })?;
+            let mut group = Filters::new();
+            group.and(&EntryFields::Amount, Operator::GreaterThanOrEqual, min_amount);
+            groups.push(group);
+        }
+        if let Some(max_amount) = &self.max_amount {
+            let max_amount: model::money::Money =
+                max_amount.parse().map_err(|e: model::money::MoneyError| {
+                    problem(Status::BadRequest, e.to_string())
+                })?;
+            let mut group = Filters::new();
+            group.and(&EntryFields::Amount, Operator::LessThanOrEqual, max_amount);
+            groups.push(group);
+        }
+        if let Some(description) = &self.description {
+            let mut group = Filters::new();
+            group.and(
+                &EntryFields::Description,
+                Operator::ILike,
+                repository::filter::contains_pattern(description),
+            );
+            groups.push(group);
+        }
+
+        let mut criteria = Filters::<EntryFields>::new();
+        for group in groups {
+            if self.any.unwrap_or(false) {
+                criteria.or_group(group);
+            } else {
+                criteria.and_group(group);
+            }
+        }
+
+        let mut filters = Filters::<EntryFields>::new();
+        if let Some(start) = &self.start_date {
+            let start = parse_date(start, false)?;
+            filters.and(&EntryFields::EventDate, Operator::GreaterThanOrEqual, start);
+        }
+        if let Some(end) = &self.end_date {
+            let end = parse_date(end, true)?;
+            filters.and(&EntryFields::EventDate, Operator::LessThanOrEqual, end);
+        }
+        filters.and_group(criteria);
+
+        Ok(filters)
+    }
+}
+
+/// Page size used when the client does not ask for one.
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Largest page size served, bigger requests are capped.
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Sort and page requested on an entry or transaction listing.
+fn entry_paging(
+    sort: Option<String>,
+    order: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<
+    (
+        repository::filter::Sort<repository::filter::EntryFields>,
+        repository::filter::Pagination,
+    ),
+    Problem,
+> {
+    use repository::filter::{EntryFields, Pagination, Sort, SortDirection};
+
+    let field = match sort.as_deref() {
+        None | Some("event_date") => EntryFields::EventDate,
+        Some("amount") => EntryFields::Amount,
+        Some("id") => EntryFields::Id,
+        Some(sort) => {
+            let detail = format!(
+                "Unknown sort key '{}', expected event_date, amount or id",
+                sort
+            );
+            return Err(problem(Status::BadRequest, detail));
+        }
+    };
+    let direction = match order.as_deref() {
+        None | Some("asc") => SortDirection::Ascending,
+        Some("desc") => SortDirection::Descending,
+        Some(order) => {
+            let detail = format!("Unknown sort order '{}', expected asc or desc", order);
+            return Err(problem(Status::BadRequest, detail));
+        }
+    };
+    let sort = Sort { field, direction };
+
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    let offset = offset.unwrap_or(0);
+    if limit < 1 || offset < 0 {
+        let detail = "limit must be positive and offset must not be negative";
+        return Err(problem(Status::BadRequest, detail));
+    }
+    let pagination = Pagination {
+        limit: limit.min(MAX_PAGE_SIZE),
+        offset,
+    };
+    Ok((sort, pagination))
+}
+
+#[utoipa::path(
+    get,
+    path = "/entries",
+    responses(
+        (status = 200, description = "Entries retrieved successfully, split ones are listed by /transactions", body = EntryPage),
+        (status = 400, description = "Malformed search criteria or paging", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("sort" = Option<String>, Query, description = "Sort key: event_date (default), amount or id"),
+        ("order" = Option<String>, Query, description = "Sort order: asc (default) or desc"),
+        ("limit" = Option<i64>, Query, description = "Page size, 100 by default and at most 1000"),
+        ("offset" = Option<i64>, Query, description = "Number of entries to skip, use next_offset from the previous page"),
+        EntrySearch
+    )
+)]
+#[get("/entries?<sort>&<order>&<limit>&<offset>&<search..>")]
+pub async fn get_entries_from_date_to_date(
+    repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
+    sort: Option<String>,
+    order: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    search: EntrySearch,
+) -> Result<Json<model::entry::EntryPage>, Problem> {
+    let filters = search.to_filters()?;
+    let (sort, pagination) = entry_paging(sort, order, limit, offset)?;
+
+    let page = repository
+        .lock()
+        .await
+        .get_entries(&filters, &sort, &pagination)
+        .await?;
+    Ok(Json(page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/transactions",
+    responses(
+        (status = 200, description = "Transactions retrieved successfully, split ones included", body = TransactionPage),
+        (status = 400, description = "Malformed search criteria or paging", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("sort" = Option<String>, Query, description = "Sort key: event_date (default), amount or id"),
+        ("order" = Option<String>, Query, description = "Sort order: asc (default) or desc"),
+        ("limit" = Option<i64>, Query, description = "Page size, 100 by default and at most 1000"),
+        ("offset" = Option<i64>, Query, description = "Number of transactions to skip, use next_offset from the previous page"),
+        EntrySearch
+    )
+)]
+#[get("/transactions?<sort>&<order>&<limit>&<offset>&<search..>")]
+pub async fn get_transactions(
+    repository: &rocket::State<Arc<Mutex<repository::Repository>>>,
+    sort: Option<String>,
+    order: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    search: EntrySearch,
+) -> Result<Json<model::transaction::TransactionPage>, Problem> {
+    let filters = search.to_filters()?;
+    let (sort, pagination) = entry_paging(sort, order, limit, offset)?;
+
+    let page = repository
+        .lock()
+        .await
+        .get_transactions(&filters, &sort, &pagination)
+        .await?;
+    Ok(Json(page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/recurrence/{id}",
+    responses(
+        (status = 200, description = "Recurrence found successfully", body = Recurrence),
+        (status = 404, description = "Recurrence not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Recurrence id")
+    )
+)]
+#[get("/recurrence/<id>")]
+pub async fn get_recurrence(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::recurrence::Recurrence>, Problem> {
+    let recurrence = repository.lock().await.get_recurrence(id).await?;
+    recurrence
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Recurrence {} not found", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/recurrences",
+    responses(
+        (status = 200, description = "Recurrences retrieved successfully", body = [Recurrence])
+    )
+)]
+#[get("/recurrences")]
+pub async fn get_recurrences(
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<Vec<model::recurrence::Recurrence>>, Problem> {
+    let recurrences = repository.lock().await.get_recurrences().await?;
+    Ok(Json(recurrences))
+}
+
+#[utoipa::path(
+    post,
+    path = "/recurrence",
+    request_body = Recurrence,
+    responses(
+        (status = 201, description = "Recurrence created successfully", body = Recurrence,
+            headers(("Location" = String, description = "Path of the created recurrence"))),
+        (status = 400, description = "Unknown or same accounts, invalid amount, or dates out of range or in the wrong order", body = Problem, content_type = "application/problem+json")
+    )
+)]
+#[post("/recurrence", data = "<recurrence>")]
+pub async fn create_recurrence(
+    recurrence: Json<model::recurrence::Recurrence>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Created<Json<model::recurrence::Recurrence>>, Problem> {
+    let recurrence = repository
+        .lock()
+        .await
+        .insert_recurrence(&recurrence.into_inner())
+        .await?;
+    let location = uri!(get_recurrence(recurrence.id.unwrap_or_default()));
+    Ok(Created::new(location.to_string()).body(Json(recurrence)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/recurrence/{id}",
+    request_body = Recurrence,
+    responses(
+        (status = 200, description = "Recurrence updated successfully", body = Recurrence),
+        (status = 400, description = "Unknown or same accounts, invalid amount, or dates out of range or in the wrong order", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Recurrence not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Recurrence id")
+    )
+)]
+#[put("/recurrence/<id>", data = "<recurrence>")]
+pub async fn update_recurrence(
+    id: i32,
+    recurrence: Json<model::recurrence::Recurrence>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::recurrence::Recurrence>, Problem> {
+    let recurrence = repository
+        .lock()
+        .await
+        .update_recurrence(id, &recurrence.into_inner())
+        .await?;
+    recurrence
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Recurrence {} not found", id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/recurrence/{id}",
+    responses(
+        (status = 204, description = "Recurrence deleted successfully, posted entries are kept"),
+        (status = 404, description = "Recurrence not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Recurrence id")
+    )
+)]
+#[delete("/recurrence/<id>")]
+pub async fn delete_recurrence(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Status, Problem> {
+    match repository.lock().await.delete_recurrence(id).await? {
+        true => Ok(Status::NoContent),
+        false => Err(problem(
+            Status::NotFound,
+            format!("Recurrence {} not found", id),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/budget/{id}",
+    responses(
+        (status = 200, description = "Budget found successfully", body = Budget),
+        (status = 404, description = "Budget not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Budget id")
+    )
+)]
+#[get("/budget/<id>")]
+pub async fn get_budget(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::budget::Budget>, Problem> {
+    let budget = repository.lock().await.get_budget(id).await?;
+    budget
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Budget {} not found", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/budgets",
+    responses(
+        (status = 200, description = "Budgets retrieved successfully", body = [Budget])
+    )
+)]
+#[get("/budgets")]
+pub async fn get_budgets(
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<Vec<model::budget::Budget>>, Problem> {
+    let budgets = repository.lock().await.get_budgets().await?;
+    Ok(Json(budgets))
+}
+
+#[utoipa::path(
+    post,
+    path = "/budget",
+    request_body = Budget,
+    responses(
+        (status = 201, description = "Budget created successfully", body = Budget,
+            headers(("Location" = String, description = "Path of the created budget"))),
+        (status = 400, description = "Unknown account, neither Expense nor Revenue account, invalid amount or frequence", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The account already has a budget with this frequence", body = Problem, content_type = "application/problem+json")
+    )
+)]
+#[post("/budget", data = "<budget>")]
+pub async fn create_budget(
+    budget: Json<model::budget::Budget>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Created<Json<model::budget::Budget>>, Problem> {
+    let budget = repository
+        .lock()
+        .await
+        .insert_budget(&budget.into_inner())
+        .await?;
+    let location = uri!(get_budget(budget.id.unwrap_or_default()));
+    Ok(Created::new(location.to_string()).body(Json(budget)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/budget/{id}",
+    request_body = Budget,
+    responses(
+        (status = 200, description = "Budget updated successfully", body = Budget),
+        (status = 400, description = "Unknown account, neither Expense nor Revenue account, invalid amount or frequence", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Budget not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The account already has a budget with this frequence", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Budget id")
+    )
+)]
+#[put("/budget/<id>", data = "<budget>")]
+pub async fn update_budget(
+    id: i32,
+    budget: Json<model::budget::Budget>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::budget::Budget>, Problem> {
+    let budget = repository
+        .lock()
+        .await
+        .update_budget(id, &budget.into_inner())
+        .await?;
+    budget
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Budget {} not found", id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/budget/{id}",
+    responses(
+        (status = 204, description = "Budget deleted successfully"),
+        (status = 404, description = "Budget not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Budget id")
+    )
+)]
+#[delete("/budget/<id>")]
+pub async fn delete_budget(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Status, Problem> {
+    match repository.lock().await.delete_budget(id).await? {
+        true => Ok(Status::NoContent),
+        false => Err(problem(
+            Status::NotFound,
+            format!("Budget {} not found", id),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/budget/{id}/status",
+    responses(
+        (status = 200, description = "Budget status computed successfully", body = BudgetStatus),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Budget not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Budget id"),
+        ("date" = Option<String>, Query, description = "Day within the month or year to report, RFC 3339 or YYYY-MM-DD, today by default")
+    )
+)]
+#[get("/budget/<id>/status?<date>")]
+pub async fn get_budget_status(
+    id: i32,
+    date: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::budget::BudgetStatus>, Problem> {
+    let date = parse_optional_day(date)?;
+
+    let status = repository.lock().await.get_budget_status(id, date).await?;
+    status
+        .map(Json)
+        .ok_or_else(|| problem(Status::NotFound, format!("Budget {} not found", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/envelopes",
+    responses(
+        (status = 200, description = "Envelopes computed successfully", body = EnvelopeMonth),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("date" = Option<String>, Query, description = "Day within the month to report, RFC 3339 or YYYY-MM-DD, today by default")
+    )
+)]
+#[get("/envelopes?<date>")]
+pub async fn get_envelopes(
+    date: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::envelope::EnvelopeMonth>, Problem> {
+    let date = parse_optional_day(date)?;
+    let envelope_month = repository.lock().await.get_envelope_month(date).await?;
+    Ok(Json(envelope_month))
+}
+
+#[utoipa::path(
+    post,
+    path = "/envelopes/sweep",
+    responses(
+        (status = 200, description = "Unspent money moved back to the pool, envelopes after the sweep", body = EnvelopeMonth),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("date" = Option<String>, Query, description = "Day within the month to sweep, RFC 3339 or YYYY-MM-DD, today by default")
+    )
+)]
+#[post("/envelopes/sweep?<date>")]
+pub async fn sweep_envelopes(
+    date: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::envelope::EnvelopeMonth>, Problem> {
+    let date = parse_optional_day(date)?;
+    let envelope_month = repository.lock().await.sweep_envelopes(date).await?;
+    Ok(Json(envelope_month))
+}
+
+#[utoipa::path(
+    get,
+    path = "/envelope-transfer/{id}",
+    responses(
+        (status = 200, description = "Envelope transfer found successfully", body = EnvelopeTransfer),
+        (status = 404, description = "Envelope transfer not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Envelope transfer id")
+    )
+)]
+#[get("/envelope-transfer/<id>")]
+pub async fn get_envelope_transfer(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<model::envelope::EnvelopeTransfer>, Problem> {
+    let transfer = repository.lock().await.get_envelope_transfer(id).await?;
+    transfer.map(Json).ok_or_else(|| {
+        problem(
+            Status::NotFound,
+            format!("Envelope transfer {} not found", id),
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/envelope-transfers",
+    responses(
+        (status = 200, description = "Envelope transfers of the month retrieved successfully", body = [EnvelopeTransfer]),
+        (status = 400, description = "Malformed date", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("date" = Option<String>, Query, description = "Day within the month, RFC 3339 or YYYY-MM-DD, today by default")
+    )
+)]
+#[get("/envelope-transfers?<date>")]
+pub async fn get_envelope_transfers(
+    date: Option<String>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Json<Vec<model::envelope::EnvelopeTransfer>>, Problem> {
+    let date = parse_optional_day(date)?;
+    let transfers = repository.lock().await.get_envelope_transfers(date).await?;
+    Ok(Json(transfers))
+}
+
+#[utoipa::path(
+    post,
+    path = "/envelope-transfer",
+    request_body = EnvelopeTransfer,
+    responses(
+        (status = 201, description = "Envelope transfer created successfully", body = EnvelopeTransfer,
+            headers(("Location" = String, description = "Path of the created transfer"))),
+        (status = 400, description = "Unknown or non Expense account, no envelope, nested envelopes or invalid amount", body = Problem, content_type = "application/problem+json")
+    )
+)]
+#[post("/envelope-transfer", data = "<transfer>")]
+pub async fn create_envelope_transfer(
+    transfer: Json<model::envelope::EnvelopeTransfer>,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Created<Json<model::envelope::EnvelopeTransfer>>, Problem> {
+    let transfer = repository
+        .lock()
+        .await
+        .insert_envelope_transfer(&transfer.into_inner())
+        .await?;
+    let location = uri!(get_envelope_transfer(transfer.id.unwrap_or_default()));
+    Ok(Created::new(location.to_string()).body(Json(transfer)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/envelope-transfer/{id}",
+    responses(
+        (status = 204, description = "Envelope transfer deleted successfully"),
+        (status = 404, description = "Envelope transfer not found", body = Problem, content_type = "application/problem+json")
+    ),
+    params(
+        ("id" = i32, Path, description = "Envelope transfer id")
+    )
+)]
+#[delete("/envelope-transfer/<id>")]
+pub async fn delete_envelope_transfer(
+    id: i32,
+    repository: &rocket::State<Arc<Mutex<Repository>>>,
+) -> Result<Status, Problem> {
+    match repository.lock().await.delete_envelope_transfer(id).await? {
+        true => Ok(Status::NoContent),
+        false => Err(problem(
+            Status::NotFound,
+            format!("Envelope transfer {} not found", id),
+        )),
+    }
+}
+
+/// Accounts followed by an `/events` subscriber.
+#[derive(Debug, FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventFilter {
+    /// Only events touching one of these account ids, repeat to follow several accounts
+    #[param(value_type = Option<Vec<i32>>)]
+    account: Vec<i32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    responses(
+        (status = 200, description = "Server-Sent Events stream, the event name is the `type` field",
+            body = LedgerEvent, content_type = "text/event-stream")
+    ),
+    params(EventFilter)
+)]
+#[get("/events?<filter..>")]
+pub async fn get_events(
+    filter: EventFilter,
+    events: &rocket::State<repository::LedgerEvents>,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let mut received = events.subscribe();
+    EventStream! {
+        loop {
+            let event = rocket::tokio::select! {
+                event = received.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    // Too slow a client misses the oldest events, the next ones still come
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
 
-    match repository.lock().await.get_entries(&filters).await {
-        Ok(entries) => Ok(Json(entries)),
-        Err(e) => {
-            eprintln!("Error retrieving entries: {}", e);
-            Err(Status::InternalServerError)
+            if event.concerns(&filter.account) {
+                yield Event::json(&event).event(event.name());
+            }
         }
     }
 }
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Deserializer};
 
 pub const FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.fZ";
@@ -9,8 +9,9 @@ where
 {
     let s = String::deserialize(deserializer)?;
 
-    DateTime::parse_from_str(&s, FORMAT)
-        .map(|dt| dt.with_timezone(&Utc))
+    // The trailing `Z` of the format is a literal, not an offset chrono could parse
+    NaiveDateTime::parse_from_str(&s, FORMAT)
+        .map(|dt| dt.and_utc())
         .map_err(serde::de::Error::custom)
 }
 
@@ -20,4 +21,48 @@ where
     S: serde::Serializer,
 {
     serializer.serialize_str(&date.format(FORMAT).to_string())
-}
\ No newline at end of file
+}
+
+/// Parses a date given as a query parameter, either RFC 3339 or a plain `YYYY-MM-DD` day.
+/// A plain day resolves to its first instant, or to its last one when `end_of_day` is set.
+pub fn parse_date_param(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, chrono::ParseError> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    let day = NaiveDate::parse_from_str(value, "%Y-%m-%d")?;
+    let time = if end_of_day {
+        NaiveTime::from_hms_micro_opt(23, 59, 59, 999_999).expect("valid time of day")
+    } else {
+        NaiveTime::MIN
+    };
+    Ok(day.and_time(time).and_utc())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_param() {
+        let start = parse_date_param("2024-12-01", false).unwrap();
+        assert_eq!(start.to_rfc3339(), "2024-12-01T00:00:00+00:00");
+
+        let end = parse_date_param("2024-12-31", true).unwrap();
+        assert_eq!(end.to_rfc3339(), "2024-12-31T23:59:59.999999+00:00");
+
+        let exact = parse_date_param("2024-12-01T10:30:00+01:00", true).unwrap();
+        assert_eq!(exact.to_rfc3339(), "2024-12-01T09:30:00+00:00");
+
+        assert!(parse_date_param("2024-12-01' OR 1=1 --", false).is_err());
+    }
+
+    #[test]
+    fn test_datefmt_deserialize() {
+        let json = serde_json::json!("2023-10-01T12:00:00.000Z");
+        let date = datefmt_deserialize(json).unwrap();
+        assert_eq!(date.to_rfc3339(), "2023-10-01T12:00:00+00:00");
+
+        assert!(datefmt_deserialize(serde_json::json!("2023-10-01")).is_err());
+    }
+}